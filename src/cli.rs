@@ -1,5 +1,9 @@
-use crate::{priority::Priority, recurrence::Recurrence, sortby::SortBy, status::Status};
-use clap::{Parser, Subcommand, ValueEnum};
+use crate::{
+    due::DueFilter, fulltext::TextQuery, output::OutputFormat, priority::Priority,
+    recurrence::Recurrence, sortby::SortBy, status::Status, table::Column,
+};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(
@@ -7,11 +11,46 @@ use clap::{Parser, Subcommand, ValueEnum};
     about = "A simple todo program with CLI or menu mode",
     long_about = "Manage your tasks via a command-line interface or interactive menu"
 )]
-
 pub struct Cli {
     #[arg(long, help = "Run in interactive menu mode")]
     pub menu: bool,
 
+    #[arg(
+        short = 'b',
+        long,
+        global = true,
+        value_enum,
+        help = "Storage backend to use (default: json)"
+    )]
+    pub backend: Option<Backend>,
+
+    #[arg(
+        short = 'f',
+        long,
+        global = true,
+        env = "TODO_FILE",
+        help = "Data file to use (default: $XDG_DATA_HOME/todoscope/)"
+    )]
+    pub file: Option<PathBuf>,
+
+    #[arg(
+        short = 'q',
+        long,
+        global = true,
+        conflicts_with = "verbose",
+        help = "Only print errors to stderr"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        short = 'v',
+        long,
+        global = true,
+        action = ArgAction::Count,
+        help = "Print progress (-v) and debug (-vv) messages to stderr"
+    )]
+    pub verbose: u8,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -27,22 +66,41 @@ pub enum Commands {
         #[arg(
             short = 'p',
             long,
-            default_value = "medium",
             value_enum,
-            help = "Set task priority"
+            help = "Set task priority (default: medium)"
         )]
-        priority: Priority,
+        priority: Option<Priority>,
         #[arg(
             short = 's',
             long,
-            default_value = "pending",
-            value_enum,
-            help = "Set initial status of the task"
+            help = "Initial status: pending, in-progress, blocked, waiting, done, cancelled \
+                    or a custom status (default: pending)"
+        )]
+        status: Option<Status>,
+        #[arg(
+            short = 'D',
+            long,
+            help = "Optional due date, e.g. 2025-10-03, \"2025-10-03 14:30\", tomorrow, \"next fri\""
         )]
-        status: Status,
-        #[arg(short = 'D', long, help = "Optional due date (format: YYY-MM-DD)")]
         due_date: Option<String>,
-        #[arg(short = 'r', long, value_enum, help = "Optional recurrence")]
+        #[arg(
+            short = 'S',
+            long,
+            help = "Day you plan to work on the task, in the same forms as --due-date"
+        )]
+        scheduled: Option<String>,
+        #[arg(
+            short = 'w',
+            long,
+            visible_alias = "start",
+            help = "Hide the task from `list` until this date, in the same forms as --due-date"
+        )]
+        wait_until: Option<String>,
+        #[arg(
+            short = 'r',
+            long,
+            help = "Optional recurrence, e.g. \"every 2 weeks on mon,thu\", \"every 3 days after completion\" or an RRULE"
+        )]
         recurrence: Option<Recurrence>,
         #[arg(
             short = 'g',
@@ -65,11 +123,30 @@ pub enum Commands {
         #[arg(
             short = 's',
             long,
-            default_value = "created",
             value_enum,
-            help = "Choose how to sort tasks"
+            help = "Choose how to sort tasks (default: created)"
+        )]
+        sort_by: Option<SortBy>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Output format (default: human, or table with --columns)"
         )]
-        sort_by: SortBy,
+        format: Option<OutputFormat>,
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            help = "Table columns, e.g. id,title,priority,due,tags"
+        )]
+        columns: Option<Vec<Column>>,
+        #[arg(short = 'a', long, help = "Also show tasks waiting until a later date")]
+        all: bool,
+        #[arg(
+            long,
+            help = "Only show tasks matching a filter, e.g. \"priority:high and not tag:home\""
+        )]
+        filter: Option<String>,
     },
     Search {
         #[arg(short = 'i', long, help = "Find task by its unique ID")]
@@ -82,16 +159,69 @@ pub enum Commands {
         title: Option<String>,
         #[arg(short = 'p', long, value_enum, help = "Find tasks by priority")]
         priority: Option<Priority>,
-        #[arg(short = 's', long, value_enum, help = "Find tasks by status")]
+        #[arg(short = 's', long, help = "Find tasks by status")]
         status: Option<Status>,
-        #[arg(short = 'D', long, help = "Find tasks by due date (format: YYY-MM-DD)")]
+        #[arg(
+            short = 'D',
+            long,
+            help = "Find tasks due on this day, e.g. 2025-10-03 or tomorrow"
+        )]
         due_date: Option<String>,
+        #[arg(
+            long,
+            help = "Find tasks due before this day, or before this time if one is given"
+        )]
+        due_before: Option<String>,
+        #[arg(
+            long,
+            help = "Find tasks due after this day, or after this time if one is given"
+        )]
+        due_after: Option<String>,
+        #[arg(long, value_enum, help = "Find tasks by when they are due")]
+        due: Option<DueFilter>,
         #[arg(short = 'r', long, help = "Find tasks by recurrence")]
         recurrence: Option<Recurrence>,
         #[arg(short = 'g', long, value_delimiter = ',', help = "FInd tasks by tags")]
         tags: Option<Vec<String>>,
         #[arg(short = 'P', long, help = "Find tasks by parent task UUID")]
         parent_task_id: Option<String>,
+        #[arg(long, help = "Find tasks created on or after this date")]
+        created_since: Option<String>,
+        #[arg(long, help = "Find tasks changed on or after this date")]
+        updated_since: Option<String>,
+        #[arg(long, help = "Find tasks started on or after this date")]
+        started_since: Option<String>,
+        #[arg(long, help = "Find tasks completed on or after this date")]
+        completed_since: Option<String>,
+        #[arg(
+            long,
+            help = "Find every occurrence of the recurring task with this ID"
+        )]
+        series: Option<String>,
+        #[arg(
+            long,
+            help = "Find tasks matching a filter, e.g. \"(tag:work or tag:urgent) and due<+3d\""
+        )]
+        filter: Option<String>,
+        #[arg(
+            long,
+            help = "Full-text search of titles, descriptions and tags, best match first, \
+                    e.g. 'report \"next week\" plan*'"
+        )]
+        text: Option<TextQuery>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Output format (default: human, or table with --columns)"
+        )]
+        format: Option<OutputFormat>,
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            help = "Table columns, e.g. id,title,priority,due,tags"
+        )]
+        columns: Option<Vec<Column>>,
     },
 
     Update {
@@ -103,11 +233,28 @@ pub enum Commands {
         description: Option<String>,
         #[arg(short = 'p', long, value_enum, help = "Update the task priority")]
         priority: Option<Priority>,
-        #[arg(short = 's', long, value_enum, help = "Update the task status")]
+        #[arg(short = 's', long, help = "Update the task status")]
         status: Option<Status>,
-        #[arg(short = 'D', long, help = "Find tasks by due date (format: YYY-MM-DD")]
+        #[arg(
+            short = 'D',
+            long,
+            help = "Update the due date, e.g. 2025-10-03, tomorrow, \"next fri\", \"in 3 days\""
+        )]
         due_date: Option<String>,
-        #[arg(short = 'r', long, value_enum, help = "Update the recurrence")]
+        #[arg(short = 'S', long, help = "Update the scheduled date")]
+        scheduled: Option<String>,
+        #[arg(
+            short = 'w',
+            long,
+            visible_alias = "start",
+            help = "Update the date the task is hidden from `list` until"
+        )]
+        wait_until: Option<String>,
+        #[arg(
+            short = 'r',
+            long,
+            help = "Update the recurrence, e.g. \"every month on the last fri\""
+        )]
         recurrence: Option<Recurrence>,
         #[arg(short = 'g', long, value_delimiter = ',', help = "Update tags")]
         tags: Option<Vec<String>>,
@@ -125,6 +272,26 @@ pub enum Commands {
         #[arg(short = 'i', long, help = "UUID of the task to remove")]
         id: String,
     },
+    /// Mark tasks as In Progress
+    Start {
+        #[arg(required = true, help = "IDs, numbers or ID prefixes of the tasks")]
+        ids: Vec<String>,
+    },
+    /// Mark tasks as Done
+    Done {
+        #[arg(required = true, help = "IDs, numbers or ID prefixes of the tasks")]
+        ids: Vec<String>,
+    },
+    /// Move tasks back to Pending
+    Reopen {
+        #[arg(required = true, help = "IDs, numbers or ID prefixes of the tasks")]
+        ids: Vec<String>,
+    },
+    /// Mark tasks as Cancelled
+    Cancel {
+        #[arg(required = true, help = "IDs, numbers or ID prefixes of the tasks")]
+        ids: Vec<String>,
+    },
     Completions {
         #[arg(
             short = 's',
@@ -135,6 +302,49 @@ pub enum Commands {
         shell: Shell,
     },
     Notify,
+    Migrate {
+        #[arg(
+            long,
+            help = "JSON file to read todos from (default: the JSON data file)"
+        )]
+        from: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "SQLite database to write todos to (default: the SQLite data file)"
+        )]
+        to: Option<PathBuf>,
+    },
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Salvage every readable todo from a damaged JSON data file
+    Repair,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the effective configuration
+    Show,
+    /// Print a single setting, e.g. `notifications.enabled`
+    Get { key: String },
+    /// Change a setting in the config file
+    Set { key: String, value: String },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Backend {
+    Json,
+    Sqlite,
+}
+
+impl Backend {
+    pub fn default_file_name(self) -> &'static str {
+        match self {
+            Backend::Json => "todos.json",
+            Backend::Sqlite => "todos.db",
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -0,0 +1,530 @@
+use crate::{
+    cli::Backend,
+    priority::Priority,
+    sortby::SortBy,
+    status::{Status, normalize},
+};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use colored::Color;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+static CURRENT: OnceLock<Config> = OnceLock::new();
+
+/// User settings read from `config.toml`. Every field is optional in the
+/// file; missing ones fall back to the built-in defaults below.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(with = "value_enum")]
+    pub default_priority: Priority,
+    #[serde(with = "status_name")]
+    pub default_status: Status,
+    #[serde(with = "value_enum")]
+    pub default_sort: SortBy,
+    pub date_format: String,
+    /// IANA time zone dates are typed and shown in, e.g. `Europe/Berlin`.
+    /// The system's time zone when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_file: Option<PathBuf>,
+    #[serde(with = "value_enum")]
+    pub backend: Backend,
+    #[serde(with = "value_enum")]
+    pub color: ColorMode,
+    pub notifications: NotificationConfig,
+    /// Statuses available on top of the built-in ones.
+    pub statuses: Vec<CustomStatus>,
+    /// Allowed status changes, keyed by the current status. Statuses
+    /// without an entry may change to any other status.
+    pub transitions: BTreeMap<String, Vec<String>>,
+    pub theme: Theme,
+}
+
+/// A user-defined status, e.g.
+///
+/// ```toml
+/// [[statuses]]
+/// name = "review"
+/// color = "cyan"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomStatus {
+    pub name: String,
+    /// Any color name `colored` understands, e.g. `cyan` or `bright blue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Closed statuses count as finished, like Done and Cancelled.
+    #[serde(default)]
+    pub closed: bool,
+}
+
+/// Colors replacing the built-in ones for priorities and statuses, e.g.
+///
+/// ```toml
+/// [theme.priority]
+/// high = "bright red"
+///
+/// [theme.status]
+/// in-progress = "cyan"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Keyed by priority name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub priority: BTreeMap<String, String>,
+    /// Keyed by status name, built-in or custom.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub status: BTreeMap<String, String>,
+}
+
+impl Theme {
+    pub fn priority_color(&self, priority: Priority) -> Option<Color> {
+        self.priority
+            .iter()
+            .find(|(name, _)| parse_priority(name) == Some(priority))
+            .and_then(|(_, color)| color.parse().ok())
+    }
+
+    pub fn status_color(&self, status: &Status) -> Option<Color> {
+        self.status
+            .iter()
+            .find(|(name, _)| status.matches(name))
+            .and_then(|(_, color)| color.parse().ok())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    pub include_overdue: bool,
+    /// Also notify about tasks that become due within this many minutes.
+    pub lead_time_minutes: i64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_priority: Priority::Medium,
+            default_status: Status::Pending,
+            default_sort: SortBy::Created,
+            date_format: "%Y-%m-%d".to_string(),
+            timezone: None,
+            data_file: None,
+            backend: Backend::Json,
+            color: ColorMode::Auto,
+            notifications: NotificationConfig::default(),
+            statuses: Vec::new(),
+            transitions: BTreeMap::new(),
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            include_overdue: true,
+            lead_time_minutes: 0,
+        }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/todoscope/config.toml`, or `config.toml` in the
+    /// current directory when no config directory can be determined.
+    pub fn default_path() -> PathBuf {
+        match dirs::config_dir() {
+            Some(dir) => dir.join("todoscope").join("config.toml"),
+            None => PathBuf::from("config.toml"),
+        }
+    }
+
+    /// Reads the config at `path`. A missing file yields the defaults.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let table = read_table(path)?;
+        Self::from_table(table)
+    }
+
+    fn from_table(table: toml::Table) -> io::Result<Self> {
+        let mut config =
+            Self::deserialize(table).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        config.validate()?;
+        if let Status::Custom(name) = &config.default_status
+            && let Some(custom) = config.custom_status(name)
+        {
+            config.default_status = Status::Custom(custom.name.clone());
+        }
+        Ok(config)
+    }
+
+    /// Checks what the types alone cannot: custom status names and colors,
+    /// and that every status mentioned elsewhere exists.
+    fn validate(&self) -> io::Result<()> {
+        for (i, custom) in self.statuses.iter().enumerate() {
+            let taken = Status::BUILT_IN
+                .iter()
+                .any(|status| status.matches(&custom.name))
+                || self.statuses[..i]
+                    .iter()
+                    .any(|other| normalize(&other.name) == normalize(&custom.name));
+            if normalize(&custom.name).is_empty() || taken {
+                return Err(invalid_data(format!(
+                    "status {:?} is empty or already defined",
+                    custom.name
+                )));
+            }
+            if let Some(color) = &custom.color
+                && color.parse::<Color>().is_err()
+            {
+                return Err(invalid_data(format!(
+                    "unknown color {color:?} for status {}",
+                    custom.name
+                )));
+            }
+        }
+
+        let known = |name: &str| {
+            Status::BUILT_IN.iter().any(|status| status.matches(name))
+                || self.custom_status(name).is_some()
+        };
+        let mentioned = std::iter::once(self.default_status.name()).chain(
+            self.transitions
+                .iter()
+                .flat_map(|(from, to)| std::iter::once(from).chain(to))
+                .map(String::as_str),
+        );
+        for name in mentioned.chain(self.theme.status.keys().map(String::as_str)) {
+            if !known(name) {
+                return Err(invalid_data(format!("unknown status {name:?}")));
+            }
+        }
+
+        if let Some(name) = self
+            .theme
+            .priority
+            .keys()
+            .find(|name| parse_priority(name).is_none())
+        {
+            return Err(invalid_data(format!("unknown priority {name:?}")));
+        }
+        for (name, color) in self.theme.priority.iter().chain(&self.theme.status) {
+            if color.parse::<Color>().is_err() {
+                return Err(invalid_data(format!(
+                    "unknown color {color:?} for {name} in the theme"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes `self` the configuration returned by [`Config::current`].
+    /// Only the first call has an effect.
+    pub fn install(self) {
+        let _ = CURRENT.set(self);
+    }
+
+    /// The installed configuration, or the defaults if none was installed.
+    pub fn current() -> &'static Config {
+        CURRENT.get_or_init(Config::default)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("config is serializable")
+    }
+
+    /// The custom status called `name`, ignoring case and punctuation.
+    pub fn custom_status(&self, name: &str) -> Option<&CustomStatus> {
+        self.statuses
+            .iter()
+            .find(|custom| normalize(&custom.name) == normalize(name))
+    }
+
+    /// Every status a todo can have: the built-ins, then custom ones.
+    pub fn all_statuses(&self) -> Vec<Status> {
+        Status::BUILT_IN
+            .into_iter()
+            .chain(
+                self.statuses
+                    .iter()
+                    .map(|custom| Status::Custom(custom.name.clone())),
+            )
+            .collect()
+    }
+
+    pub fn allows_transition(&self, from: &Status, to: &Status) -> bool {
+        if from == to {
+            return true;
+        }
+        match self.transitions.iter().find(|(name, _)| from.matches(name)) {
+            Some((_, allowed)) => allowed.iter().any(|name| to.matches(name)),
+            None => true,
+        }
+    }
+
+    /// Looks up a value by dotted key, e.g. `notifications.enabled`.
+    pub fn get(&self, key: &str) -> Option<toml::Value> {
+        let mut value = toml::Value::try_from(self).ok()?;
+        for part in key.split('.') {
+            value = value.as_table_mut()?.remove(part)?;
+        }
+        Some(value)
+    }
+}
+
+/// Sets `key` to `value` in the config file at `path`, leaving other
+/// settings untouched. The value is rejected if the result is invalid.
+pub fn set_value(path: &Path, key: &str, value: &str) -> io::Result<()> {
+    let mut table = read_table(path)?;
+    let value = parse_value(value);
+
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop().unwrap_or_default();
+    let mut target = &mut table;
+    for part in parts {
+        target = target
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| invalid_key(key))?;
+    }
+    target.insert(last.to_string(), value);
+
+    Config::from_table(table.clone())?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(
+        path,
+        toml::to_string_pretty(&table).map_err(io::Error::other)?,
+    )
+}
+
+fn read_table(path: &Path) -> io::Result<toml::Table> {
+    match fs::read_to_string(path) {
+        Ok(data) => data
+            .parse::<toml::Table>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(toml::Table::new()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Interprets `true`, `15` etc. as TOML values and anything else as a string.
+fn parse_value(raw: &str) -> toml::Value {
+    format!("value = {raw}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+fn parse_priority(name: &str) -> Option<Priority> {
+    Priority::from_str(name, true).ok()
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn invalid_key(key: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{key} is not a valid config key"),
+    )
+}
+
+/// Stores clap value enums by their command-line name (e.g. `in-progress`)
+/// so the config file accepts the same spellings as the flags.
+mod value_enum {
+    use clap::ValueEnum;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer, T: ValueEnum>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let possible = value
+            .to_possible_value()
+            .expect("config enums have no skipped variants");
+        serializer.serialize_str(possible.get_name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: ValueEnum>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        T::from_str(&raw, true).map_err(D::Error::custom)
+    }
+}
+
+/// Stores statuses by their command-line name, like [`value_enum`] does
+/// for the other enums.
+mod status_name {
+    use crate::status::Status;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(status: &Status, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(status.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Status, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_config_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("todoscope-{}", uuid::Uuid::new_v4()))
+            .join("config.toml")
+    }
+
+    #[test]
+    fn missing_file_uses_defaults() {
+        assert_eq!(
+            Config::load(&temp_config_path()).unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn set_value_round_trips_through_file() {
+        let path = temp_config_path();
+        set_value(&path, "default_priority", "high").unwrap();
+        set_value(&path, "notifications.lead_time_minutes", "30").unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.default_priority, Priority::High);
+        assert_eq!(config.notifications.lead_time_minutes, 30);
+        assert_eq!(
+            config.get("default_priority").unwrap().as_str(),
+            Some("high")
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn set_value_rejects_invalid_settings() {
+        let path = temp_config_path();
+        assert!(set_value(&path, "default_status", "someday").is_err());
+        assert!(set_value(&path, "no_such_key", "1").is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn custom_statuses_and_transitions() {
+        let config = Config::from_table(
+            r#"
+            default_status = "triage"
+
+            [[statuses]]
+            name = "Triage"
+            color = "cyan"
+
+            [[statuses]]
+            name = "Won't fix"
+            closed = true
+
+            [transitions]
+            triage = ["pending", "wont-fix"]
+            done = ["pending"]
+            "#
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let triage = Status::Custom("Triage".into());
+        let wont_fix = Status::Custom("Won't fix".into());
+
+        assert_eq!(config.default_status, triage);
+        assert_eq!(config.all_statuses().len(), Status::BUILT_IN.len() + 2);
+        assert!(config.allows_transition(&triage, &wont_fix));
+        assert!(!config.allows_transition(&triage, &Status::Done));
+        assert!(!config.allows_transition(&Status::Done, &Status::InProgress));
+        assert!(config.allows_transition(&Status::Pending, &triage));
+        assert!(config.custom_status("WONT FIX").unwrap().closed);
+    }
+
+    #[test]
+    fn rejects_unknown_and_duplicate_statuses() {
+        for toml in [
+            "[[statuses]]\nname = \"done\"",
+            "[[statuses]]\nname = \"review\"\n[[statuses]]\nname = \"Review\"",
+            "[[statuses]]\nname = \"review\"\ncolor = \"plaid\"",
+            "[transitions]\npending = [\"someday\"]",
+        ] {
+            assert!(Config::from_table(toml.parse().unwrap()).is_err(), "{toml}");
+        }
+    }
+
+    #[test]
+    fn theme_colors_priorities_and_statuses() {
+        let config = Config::from_table(
+            r#"
+            [[statuses]]
+            name = "review"
+            color = "cyan"
+
+            [theme.priority]
+            High = "bright red"
+
+            [theme.status]
+            in_progress = "blue"
+            review = "magenta"
+            "#
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.theme.priority_color(Priority::High),
+            Some(Color::BrightRed)
+        );
+        assert_eq!(config.theme.priority_color(Priority::Low), None);
+        assert_eq!(
+            config.theme.status_color(&Status::InProgress),
+            Some(Color::Blue)
+        );
+        assert_eq!(
+            config.theme.status_color(&Status::Custom("review".into())),
+            Some(Color::Magenta)
+        );
+        assert_eq!(config.theme.status_color(&Status::Done), None);
+
+        for toml in [
+            "[theme.priority]\nurgent = \"red\"",
+            "[theme.priority]\nhigh = \"plaid\"",
+            "[theme.status]\nsomeday = \"red\"",
+            "[theme.status]\ndone = \"plaid\"",
+        ] {
+            assert!(Config::from_table(toml.parse().unwrap()).is_err(), "{toml}");
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{
+    config::Config,
+    due::Due,
+    error::{TodoError, TodoResult},
+};
+use chrono::{
+    DateTime, Datelike, Days, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+/// The time zone dates are typed and shown in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    /// The system's time zone.
+    Local,
+    Named(Tz),
+}
+
+impl Zone {
+    /// `timezone` from the config file, or the system's time zone.
+    pub fn current() -> Self {
+        Config::current().timezone.map_or(Zone::Local, Zone::Named)
+    }
+
+    /// The wall-clock time in this zone at `at`.
+    pub fn local(self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Local => at.with_timezone(&Local).naive_local(),
+            Zone::Named(tz) => at.with_timezone(&tz).naive_local(),
+        }
+    }
+
+    /// The moment a wall-clock time in this zone refers to. Times that
+    /// happen twice when clocks go back take the first; times skipped when
+    /// clocks go forward move an hour later.
+    pub fn utc(self, local: NaiveDateTime) -> DateTime<Utc> {
+        fn resolve(zone: &impl TimeZone, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+            zone.from_local_datetime(&local)
+                .earliest()
+                .map(|at| at.with_timezone(&Utc))
+        }
+        let later = local + Duration::hours(1);
+        match self {
+            Zone::Local => resolve(&Local, local).or_else(|| resolve(&Local, later)),
+            Zone::Named(tz) => resolve(&tz, local).or_else(|| resolve(&tz, later)),
+        }
+        .unwrap_or_else(|| local.and_utc())
+    }
+
+    /// The start of `date` in this zone.
+    pub fn midnight(self, date: NaiveDate) -> DateTime<Utc> {
+        self.utc(date.and_time(NaiveTime::MIN))
+    }
+
+    pub fn today(self) -> NaiveDate {
+        self.local(Utc::now()).date()
+    }
+}
+
+/// Parses a due date typed on the command line or in the menu.
+///
+/// Besides a date in the configured `format` this accepts ISO 8601
+/// (`2025-10-03`, `2025-10-03 14:30`, `2025-10-03T14:30:00+02:00`) and
+/// relative forms: `today`, `tomorrow`, `yesterday`, `friday`,
+/// `next friday`, `in 3 days`, `in 2 weeks`, `in 4 hours`, and `eow`,
+/// `eom`, `eoy` for the end of the week, month or year. Relative days take
+/// a time too (`tomorrow 14:30`). Dates without a time are all-day; times
+/// without an offset are in [`Zone::current`].
+pub fn parse_due(input: &str, format: &str) -> TodoResult<Due> {
+    parse_due_at(input, format, Utc::now(), Zone::current())
+}
+
+/// [`parse_due`] for filters that need a moment: dates mean their start.
+pub fn parse_date(input: &str, format: &str) -> TodoResult<DateTime<Utc>> {
+    Ok(parse_due(input, format)?.start(Zone::current()))
+}
+
+/// [`parse_due`] with relative forms counted from `now` in `zone`.
+pub fn parse_due_at(input: &str, format: &str, now: DateTime<Utc>, zone: Zone) -> TodoResult<Due> {
+    let input = input.trim();
+    let input_lower = input.to_lowercase();
+    parse_absolute(input, format, zone)
+        .or_else(|| parse_relative(&input_lower, now, zone))
+        .or_else(|| {
+            let (day, time) = input_lower.rsplit_once(' ')?;
+            let time = NaiveTime::parse_from_str(time, "%H:%M").ok()?;
+            match parse_relative(day, now, zone)? {
+                Due::Date(date) => Some(Due::At(zone.utc(date.and_time(time)))),
+                Due::At(_) => None,
+            }
+        })
+        .ok_or_else(|| TodoError::InvalidDate {
+            input: input.to_string(),
+            expected: format!(
+                "{format}, optionally followed by HH:MM, an ISO 8601 timestamp, \
+                 or e.g. today, tomorrow 9:00, next friday, in 3 days, eow"
+            ),
+        })
+}
+
+fn parse_absolute(input: &str, format: &str, zone: Zone) -> Option<Due> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(input) {
+        return Some(Due::At(timestamp.with_timezone(&Utc)));
+    }
+    let with_time = [
+        format!("{format} %H:%M"),
+        format!("{format} %H:%M:%S"),
+        "%Y-%m-%d %H:%M".into(),
+        "%Y-%m-%d %H:%M:%S".into(),
+        "%Y-%m-%dT%H:%M".into(),
+        "%Y-%m-%dT%H:%M:%S".into(),
+    ];
+    if let Some(local) = with_time
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    {
+        return Some(Due::At(zone.utc(local)));
+    }
+    [format, "%Y-%m-%d"]
+        .into_iter()
+        .find_map(|format| NaiveDate::parse_from_str(input, format).ok())
+        .map(Due::Date)
+}
+
+fn parse_relative(input: &str, now: DateTime<Utc>, zone: Zone) -> Option<Due> {
+    let today = zone.local(now).date();
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let date = match words[..] {
+        ["today"] => today,
+        ["tomorrow"] => today.succ_opt()?,
+        ["yesterday"] => today.pred_opt()?,
+        ["eow"] => next_weekday(today.pred_opt()?, Weekday::Sun),
+        ["eom"] => today
+            .with_day(1)?
+            .checked_add_months(Months::new(1))?
+            .pred_opt()?,
+        ["eoy"] => NaiveDate::from_ymd_opt(today.year(), 12, 31)?,
+        ["next", day] | [day] => next_weekday(today, day.parse().ok()?),
+        ["in", count, unit] => {
+            let count: u32 = count.parse().ok()?;
+            match unit.trim_end_matches('s') {
+                "minute" | "min" => {
+                    return now
+                        .checked_add_signed(Duration::minutes(count.into()))
+                        .map(Due::At);
+                }
+                "hour" => {
+                    return now
+                        .checked_add_signed(Duration::hours(count.into()))
+                        .map(Due::At);
+                }
+                "day" => today.checked_add_days(Days::new(count.into()))?,
+                "week" => today.checked_add_days(Days::new(7 * u64::from(count)))?,
+                "month" => today.checked_add_months(Months::new(count))?,
+                "year" => today.checked_add_months(Months::new(count.checked_mul(12)?))?,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    Some(Due::Date(date))
+}
+
+/// The first `weekday` after `date`.
+fn next_weekday(date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (7 + weekday.num_days_from_monday() - date.weekday().num_days_from_monday()) % 7;
+    date + Days::new(if ahead == 0 { 7 } else { ahead.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEW_YORK: Zone = Zone::Named(Tz::America__New_York);
+
+    fn parse(input: &str) -> String {
+        // A Thursday afternoon in New York.
+        let now = Utc.with_ymd_and_hms(2025, 10, 2, 19, 0, 0).unwrap();
+        match parse_due_at(input, "%d.%m.%Y", now, NEW_YORK).unwrap() {
+            Due::Date(date) => date.to_string(),
+            Due::At(at) => at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_absolute_dates() {
+        assert_eq!(parse("03.10.2025"), "2025-10-03");
+        assert_eq!(parse("2025-10-03"), "2025-10-03");
+        assert_eq!(parse("03.10.2025 14:30"), "2025-10-03 18:30 UTC");
+        assert_eq!(parse(" 2025-10-03 14:30 "), "2025-10-03 18:30 UTC");
+        assert_eq!(parse("2025-10-03T14:30:00+02:00"), "2025-10-03 12:30 UTC");
+        assert_eq!(parse("2025-10-03T14:30:00Z"), "2025-10-03 14:30 UTC");
+    }
+
+    #[test]
+    fn parses_relative_dates() {
+        assert_eq!(parse("Today"), "2025-10-02");
+        assert_eq!(parse("tomorrow"), "2025-10-03");
+        assert_eq!(parse("next friday"), "2025-10-03");
+        assert_eq!(parse("thu"), "2025-10-09");
+        assert_eq!(parse("in 3 days"), "2025-10-05");
+        assert_eq!(parse("in 1 week"), "2025-10-09");
+        assert_eq!(parse("in 2 hours"), "2025-10-02 21:00 UTC");
+        assert_eq!(parse("tomorrow 9:15"), "2025-10-03 13:15 UTC");
+        assert_eq!(parse("eow"), "2025-10-05");
+        assert_eq!(parse("eom"), "2025-10-31");
+        assert_eq!(parse("eoy"), "2025-12-31");
+    }
+
+    #[test]
+    fn counts_relative_dates_in_the_local_zone() {
+        // Already Friday in UTC, still Thursday evening in New York.
+        let now = Utc.with_ymd_and_hms(2025, 10, 3, 2, 0, 0).unwrap();
+        let today = parse_due_at("today", "%Y-%m-%d", now, NEW_YORK).unwrap();
+        assert_eq!(
+            today,
+            Due::Date(NaiveDate::from_ymd_opt(2025, 10, 2).unwrap())
+        );
+    }
+
+    #[test]
+    fn resolves_daylight_saving_gaps_and_overlaps() {
+        let local = |text| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        // 02:30 does not exist on 2025-03-09 in New York.
+        assert_eq!(
+            NEW_YORK.utc(local("2025-03-09 02:30")).to_rfc3339(),
+            "2025-03-09T07:30:00+00:00"
+        );
+        // 01:30 happens twice on 2025-11-02; the first one is in EDT.
+        assert_eq!(
+            NEW_YORK.utc(local("2025-11-02 01:30")).to_rfc3339(),
+            "2025-11-02T05:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn explains_what_it_expected() {
+        let error = parse_due_at("someday", "%Y-%m-%d", Utc::now(), NEW_YORK).unwrap_err();
+        assert!(matches!(error, TodoError::InvalidDate { .. }));
+        assert!(error.to_string().contains("next friday"), "{error}");
+        assert!(parse_due_at("in x days", "%Y-%m-%d", Utc::now(), NEW_YORK).is_err());
+        for input in ["in 400000000 years", "in 4294967295 months"] {
+            let error = parse_due_at(input, "%Y-%m-%d", Utc::now(), NEW_YORK).unwrap_err();
+            assert!(matches!(error, TodoError::InvalidDate { .. }), "{input}");
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{config::Config, dates::Zone};
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeDelta, Utc, Weekday};
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+
+/// When a todo is due, scheduled or waits until: some time on a calendar
+/// day, or at an exact moment.
+///
+/// All-day dates are stored as the date itself, so they stay on the
+/// same day whichever time zone they are looked at from. Timed ones are
+/// stored in UTC and shown in [`Zone::current`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Due {
+    Date(NaiveDate),
+    At(DateTime<Utc>),
+}
+
+impl Due {
+    /// The day it is due on in `zone`.
+    pub fn date(self, zone: Zone) -> NaiveDate {
+        match self {
+            Due::Date(date) => date,
+            Due::At(at) => zone.local(at).date(),
+        }
+    }
+
+    /// When it starts being due: the start of the day for all-day todos.
+    pub fn start(self, zone: Zone) -> DateTime<Utc> {
+        match self {
+            Due::Date(date) => zone.midnight(date),
+            Due::At(at) => at,
+        }
+    }
+
+    /// All-day todos are overdue once their day is over in `zone`, timed
+    /// ones as soon as the time has passed.
+    pub fn is_overdue_at(self, now: DateTime<Utc>, zone: Zone) -> bool {
+        match self {
+            Due::Date(date) => zone.local(now).date() > date,
+            Due::At(at) => now > at,
+        }
+    }
+
+    /// Whether it is still ahead: all-day dates until their day begins in
+    /// `zone`, timed ones until the time.
+    pub fn is_future_at(self, now: DateTime<Utc>, zone: Zone) -> bool {
+        match self {
+            Due::Date(date) => zone.local(now).date() < date,
+            Due::At(at) => now < at,
+        }
+    }
+
+    /// Whether it falls before `bound`: before its day when `bound` is
+    /// all-day, before the moment otherwise.
+    pub fn is_before(self, bound: Due, zone: Zone) -> bool {
+        self.start(zone) < bound.start(zone)
+    }
+
+    /// Whether it falls after `bound`: after its day when `bound` is
+    /// all-day, after the moment otherwise.
+    pub fn is_after(self, bound: Due, zone: Zone) -> bool {
+        match bound {
+            Due::Date(day) => self.date(zone) > day,
+            Due::At(at) => self.start(zone) > at,
+        }
+    }
+
+    /// Moved by `delta` on the calendar, keeping the local time of day.
+    pub fn shifted(self, delta: TimeDelta, zone: Zone) -> Self {
+        match self {
+            Due::Date(date) => Due::Date(date + delta),
+            Due::At(at) => Due::At(zone.utc(zone.local(at) + delta)),
+        }
+    }
+
+    /// `date_format`, followed by the local time for timed todos.
+    pub fn format(self, date_format: &str, zone: Zone) -> String {
+        match self {
+            Due::Date(date) => date.format(date_format).to_string(),
+            Due::At(at) => zone
+                .local(at)
+                .format(&format!("{date_format} %H:%M"))
+                .to_string(),
+        }
+    }
+
+    /// Sort key: by day, with timed todos before the all-day ones that
+    /// may be done any time that day.
+    fn key(self, zone: Zone) -> (NaiveDate, bool, DateTime<Utc>) {
+        (
+            self.date(zone),
+            matches!(self, Due::Date(_)),
+            self.start(zone),
+        )
+    }
+}
+
+impl Display for Due {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(&Config::current().date_format, Zone::current()))
+    }
+}
+
+impl Ord for Due {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let zone = Zone::current();
+        self.key(zone).cmp(&other.key(zone))
+    }
+}
+
+impl PartialOrd for Due {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A named range of due dates for `search --due`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum DueFilter {
+    /// Due some time today.
+    Today,
+    /// Due between Monday and Sunday of the current week.
+    ThisWeek,
+    Overdue,
+    /// Without a due date.
+    None,
+}
+
+impl DueFilter {
+    /// Whether a todo due at `due` is in this range at `now` in `zone`.
+    pub fn matches(self, due: Option<Due>, now: DateTime<Utc>, zone: Zone) -> bool {
+        let today = zone.local(now).date();
+        match (self, due) {
+            (DueFilter::None, due) => due.is_none(),
+            (_, None) => false,
+            (DueFilter::Today, Some(due)) => due.date(zone) == today,
+            (DueFilter::ThisWeek, Some(due)) => {
+                let week = today.week(Weekday::Mon);
+                (week.first_day()..=week.last_day()).contains(&due.date(zone))
+            }
+            (DueFilter::Overdue, Some(due)) => due.is_overdue_at(now, zone),
+        }
+    }
+}
+
+/// Stored as `2025-10-03` for all-day todos and as an RFC 3339 timestamp
+/// in UTC otherwise.
+impl Serialize for Due {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Due::Date(date) => serializer.collect_str(&date.format("%Y-%m-%d")),
+            Due::At(at) => {
+                serializer.serialize_str(&at.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Due {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if let Ok(date) = NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+            return Ok(Due::Date(date));
+        }
+        DateTime::parse_from_rfc3339(&text)
+            .map(|at| Due::At(at.with_timezone(&Utc)))
+            .map_err(|e| D::Error::custom(format!("invalid due date {text:?}: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+
+    const TOKYO: Zone = Zone::Named(Tz::Asia__Tokyo);
+    const LOS_ANGELES: Zone = Zone::Named(Tz::America__Los_Angeles);
+
+    #[test]
+    fn all_day_todos_are_overdue_after_their_local_day() {
+        let due = Due::Date(NaiveDate::from_ymd_opt(2025, 10, 3).unwrap());
+        // 19:00 on the 3rd in Los Angeles, already the 4th in UTC.
+        let evening = Utc.with_ymd_and_hms(2025, 10, 4, 2, 0, 0).unwrap();
+        assert!(!due.is_overdue_at(evening, LOS_ANGELES));
+        // 11:00 on the 4th in Tokyo.
+        assert!(due.is_overdue_at(evening, TOKYO));
+
+        let timed = Due::At(Utc.with_ymd_and_hms(2025, 10, 4, 1, 0, 0).unwrap());
+        assert!(timed.is_overdue_at(evening, LOS_ANGELES));
+    }
+
+    #[test]
+    fn waits_until_the_local_day_begins() {
+        let start = Due::Date(NaiveDate::from_ymd_opt(2025, 10, 4).unwrap());
+        // Still the 3rd in Los Angeles, already the 4th in Tokyo.
+        let now = Utc.with_ymd_and_hms(2025, 10, 4, 2, 0, 0).unwrap();
+        assert!(start.is_future_at(now, LOS_ANGELES));
+        assert!(!start.is_future_at(now, TOKYO));
+    }
+
+    #[test]
+    fn compares_with_day_and_time_bounds() {
+        let day = |d| Due::Date(NaiveDate::from_ymd_opt(2025, 10, d).unwrap());
+        let evening = Due::At(Utc.with_ymd_and_hms(2025, 10, 3, 18, 0, 0).unwrap());
+        let zone = Zone::Named(Tz::UTC);
+        assert!(day(2).is_before(day(3), zone));
+        assert!(!day(3).is_before(day(3), zone));
+        assert!(!evening.is_before(day(3), zone));
+        assert!(day(3).is_before(evening, zone));
+        assert!(day(4).is_after(day(3), zone));
+        assert!(!evening.is_after(day(3), zone));
+        assert!(!day(3).is_after(evening, zone));
+    }
+
+    #[test]
+    fn matches_named_ranges() {
+        // Thursday, 2 October 2025.
+        let now = Utc.with_ymd_and_hms(2025, 10, 2, 12, 0, 0).unwrap();
+        let day = |d| Some(Due::Date(NaiveDate::from_ymd_opt(2025, 10, d).unwrap()));
+        let matching = |filter: DueFilter| {
+            (1..=7)
+                .filter(|&d| filter.matches(day(d), now, TOKYO))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matching(DueFilter::Today), [2]);
+        assert_eq!(matching(DueFilter::ThisWeek), [1, 2, 3, 4, 5]);
+        assert_eq!(matching(DueFilter::Overdue), [1]);
+        assert!(DueFilter::None.matches(None, now, TOKYO));
+        assert!(!DueFilter::None.matches(day(2), now, TOKYO));
+        assert!(!DueFilter::Overdue.matches(None, now, TOKYO));
+    }
+
+    #[test]
+    fn shows_times_in_the_local_zone() {
+        let due = Due::At(Utc.with_ymd_and_hms(2025, 10, 4, 1, 30, 0).unwrap());
+        assert_eq!(due.format("%Y-%m-%d", LOS_ANGELES), "2025-10-03 18:30");
+        assert_eq!(due.format("%d.%m.%Y", TOKYO), "04.10.2025 10:30");
+        assert_eq!(due.date(LOS_ANGELES).to_string(), "2025-10-03");
+    }
+
+    #[test]
+    fn stores_dates_and_utc_timestamps() {
+        let dues = [
+            Due::Date(NaiveDate::from_ymd_opt(2025, 10, 3).unwrap()),
+            Due::At(Utc.with_ymd_and_hms(2025, 10, 3, 14, 30, 0).unwrap()),
+        ];
+        let json = serde_json::to_string(&dues).unwrap();
+        assert_eq!(json, r#"["2025-10-03","2025-10-03T14:30:00Z"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Due>>(&json).unwrap(), dues);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::status::Status;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io,
+    path::PathBuf,
+};
+use unicode_width::UnicodeWidthStr;
+use uuid::Uuid;
+
+pub type TodoResult<T> = Result<T, TodoError>;
+
+#[derive(Debug)]
+pub enum TodoError {
+    /// No todo with this ID exists.
+    NotFound(Uuid),
+    /// A todo with this ID already exists.
+    AlreadyExists(Uuid),
+    /// The given text is not a valid todo ID, number or ID prefix.
+    InvalidId(String),
+    /// No todo matches the given number or ID prefix.
+    UnknownId(String),
+    /// More than one todo starts with the given ID prefix.
+    AmbiguousId {
+        input: String,
+        candidates: Vec<(Uuid, String)>,
+    },
+    /// The given text is not a date in the expected format.
+    InvalidDate {
+        input: String,
+        expected: String,
+    },
+    /// A filter expression could not be parsed; `position` is the byte
+    /// offset in `filter` the problem was found at.
+    InvalidFilter {
+        filter: String,
+        position: usize,
+        message: String,
+    },
+    /// The status is neither built in nor defined in the config file.
+    UnknownStatus(String),
+    /// The config's `[transitions]` table forbids this status change.
+    Transition {
+        from: Status,
+        to: Status,
+    },
+    /// Making `parent` the parent of `id` would create a loop.
+    Cycle {
+        id: Uuid,
+        parent: Uuid,
+    },
+    /// The command does not work with the chosen backend.
+    Unsupported(String),
+    /// Stored data exists but could not be parsed. Nothing is written over
+    /// it until it has been repaired.
+    Parse {
+        path: PathBuf,
+        reason: String,
+    },
+    /// Reading or writing stored data failed.
+    Storage(io::Error),
+    Database(rusqlite::Error),
+    /// The config file could not be read or a setting is invalid.
+    Config(io::Error),
+}
+
+impl TodoError {
+    /// Process exit code for this error, so scripts can tell failures apart:
+    ///
+    /// | code | meaning                         |
+    /// |------|---------------------------------|
+    /// | 2    | invalid or ambiguous input, or  |
+    /// |      | an unsupported command          |
+    /// | 3    | todo not found                  |
+    /// | 4    | conflicting change (ID, cycle,  |
+    /// |      | forbidden status change)        |
+    /// | 5    | data file is corrupt            |
+    /// | 6    | storage failure                 |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TodoError::InvalidId(_)
+            | TodoError::AmbiguousId { .. }
+            | TodoError::InvalidDate { .. }
+            | TodoError::InvalidFilter { .. }
+            | TodoError::UnknownStatus(_)
+            | TodoError::Unsupported(_)
+            | TodoError::Config(_) => 2,
+            TodoError::NotFound(_) | TodoError::UnknownId(_) => 3,
+            TodoError::AlreadyExists(_)
+            | TodoError::Cycle { .. }
+            | TodoError::Transition { .. } => 4,
+            TodoError::Parse { .. } => 5,
+            TodoError::Storage(_) | TodoError::Database(_) => 6,
+        }
+    }
+}
+
+impl Display for TodoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TodoError::NotFound(id) => write!(f, "no todo found with id {id}"),
+            TodoError::AlreadyExists(id) => write!(f, "a todo with id {id} already exists"),
+            TodoError::InvalidId(input) => write!(f, "invalid todo id: {input}"),
+            TodoError::UnknownId(input) => write!(f, "no todo matches {input}"),
+            TodoError::AmbiguousId { input, candidates } => {
+                write!(f, "{input} is ambiguous, it matches:")?;
+                for (id, title) in candidates {
+                    write!(f, "\n  {id}  {title}")?;
+                }
+                Ok(())
+            }
+            TodoError::InvalidDate { input, expected } => {
+                write!(f, "invalid date: {input} (expected {expected})")
+            }
+            TodoError::InvalidFilter {
+                filter,
+                position,
+                message,
+            } => {
+                let column = filter[..*position].width();
+                write!(
+                    f,
+                    "invalid filter: {message}\n  {filter}\n  {:column$}^",
+                    ""
+                )
+            }
+            TodoError::UnknownStatus(name) => write!(
+                f,
+                "unknown status: {name} (custom statuses go under [[statuses]] in the config file)"
+            ),
+            TodoError::Transition { from, to } => {
+                write!(f, "cannot change status from {from} to {to}")
+            }
+            TodoError::Cycle { id, parent } => {
+                write!(f, "making {parent} the parent of {id} would create a cycle")
+            }
+            TodoError::Unsupported(message) => f.write_str(message),
+            TodoError::Parse { path, reason } => write!(
+                f,
+                "{} is corrupt ({reason}); run `todo repair` to salvage it",
+                path.display()
+            ),
+            TodoError::Storage(error) => write!(f, "storage error: {error}"),
+            TodoError::Database(error) => write!(f, "database error: {error}"),
+            TodoError::Config(error) => write!(f, "config error: {error}"),
+        }
+    }
+}
+
+impl Error for TodoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TodoError::Storage(error) | TodoError::Config(error) => Some(error),
+            TodoError::Database(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TodoError {
+    fn from(error: io::Error) -> Self {
+        TodoError::Storage(error)
+    }
+}
+
+impl From<serde_json::Error> for TodoError {
+    fn from(error: serde_json::Error) -> Self {
+        TodoError::Storage(error.into())
+    }
+}
+
+impl From<rusqlite::Error> for TodoError {
+    fn from(error: rusqlite::Error) -> Self {
+        TodoError::Database(error)
+    }
+}
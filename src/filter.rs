@@ -0,0 +1,724 @@
+use crate::{
+    config::Config,
+    dates::{Zone, parse_due_at},
+    due::{Due, DueFilter},
+    error::{TodoError, TodoResult},
+    priority::Priority,
+    status::Status,
+    storage::TodoQuery,
+    todo::Todo,
+};
+use chrono::{DateTime, Duration, Months, Utc};
+use clap::ValueEnum;
+use std::cmp::Ordering;
+
+/// A filter expression such as
+/// `priority:high and (tag:work or tag:urgent) and not status:done and due<+3d`.
+///
+/// Conditions are `field:value`, or `field` followed by `=`, `!=`, `<`,
+/// `<=`, `>` or `>=` and a value; a word without a field matches the title
+/// or description. `not` binds tighter than `and`, which binds tighter
+/// than `or`, and conditions written next to each other are joined with
+/// `and`. Values with spaces go in double quotes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Condition(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The title or description contains this lowercase text.
+    Text(String),
+    Title(String),
+    Description(String),
+    /// Has this tag, ignoring case.
+    Tag(String),
+    /// Compared by importance: `priority>medium` means High.
+    Priority(Comparison, Priority),
+    Status(Status),
+    Date(DateField, Comparison, DateValue),
+    Is(State),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Due,
+    Scheduled,
+    Wait,
+    Created,
+    Updated,
+    Started,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateValue {
+    /// On this day with `:`, or before or after it (see [`Due::is_before`]).
+    Date(Due),
+    /// A named range; `none` matches todos without the date.
+    Range(DueFilter),
+    /// Any todo that has the date.
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum State {
+    Open,
+    Closed,
+    Overdue,
+    Deferred,
+    Recurring,
+}
+
+const FIELDS: &str = "title, description, tag, priority, status, is, due, scheduled, wait, \
+                      created, updated, started, completed";
+
+impl Filter {
+    /// Parses `input`, reading dates in the configured format and zone.
+    pub fn parse(input: &str) -> TodoResult<Self> {
+        Self::parse_at(
+            input,
+            &Config::current().date_format,
+            Utc::now(),
+            Zone::current(),
+        )
+    }
+
+    /// [`Filter::parse`] with relative dates counted from `now` in `zone`.
+    pub fn parse_at(input: &str, format: &str, now: DateTime<Utc>, zone: Zone) -> TodoResult<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            input,
+            tokens,
+            next: 0,
+            format,
+            now,
+            zone,
+        };
+        if parser.tokens.is_empty() {
+            return Err(parser.error(0, "the filter is empty"));
+        }
+        let filter = parser.or()?;
+        match parser.tokens.get(parser.next) {
+            Some(token) => Err(parser.error(token.start, "this `)` has no matching `(`")),
+            None => Ok(filter),
+        }
+    }
+
+    pub fn matches(&self, todo: &Todo) -> bool {
+        self.matches_at(todo, Utc::now(), Zone::current())
+    }
+
+    /// The conditions every match has to meet that a store can look up in
+    /// its indexes: status, priority and due-before conditions that are not
+    /// under an `or` or `not`.
+    pub fn query(&self) -> TodoQuery {
+        let mut query = TodoQuery::default();
+        self.narrow(&mut query);
+        query
+    }
+
+    fn narrow(&self, query: &mut TodoQuery) {
+        match self {
+            Filter::And(left, right) => {
+                left.narrow(query);
+                right.narrow(query);
+            }
+            Filter::Condition(Condition::Status(status)) => query.status = Some(status.clone()),
+            Filter::Condition(Condition::Priority(Comparison::Eq, priority)) => {
+                query.priority = Some(*priority)
+            }
+            Filter::Condition(Condition::Date(
+                DateField::Due,
+                Comparison::Lt,
+                DateValue::Date(bound),
+            )) => query.due_before = Some(*bound),
+            _ => {}
+        }
+    }
+
+    /// [`Filter::matches`] as if it were `now` in `zone`.
+    pub fn matches_at(&self, todo: &Todo, now: DateTime<Utc>, zone: Zone) -> bool {
+        match self {
+            Filter::And(left, right) => {
+                left.matches_at(todo, now, zone) && right.matches_at(todo, now, zone)
+            }
+            Filter::Or(left, right) => {
+                left.matches_at(todo, now, zone) || right.matches_at(todo, now, zone)
+            }
+            Filter::Not(inner) => !inner.matches_at(todo, now, zone),
+            Filter::Condition(condition) => condition.matches_at(todo, now, zone),
+        }
+    }
+}
+
+impl Condition {
+    fn matches_at(&self, todo: &Todo, now: DateTime<Utc>, zone: Zone) -> bool {
+        let contains = |text: &str, needle: &str| text.to_lowercase().contains(needle);
+        let description = |needle: &str| {
+            todo.description
+                .as_deref()
+                .is_some_and(|description| contains(description, needle))
+        };
+        match self {
+            Condition::Text(text) => contains(&todo.title, text) || description(text),
+            Condition::Title(text) => contains(&todo.title, text),
+            Condition::Description(text) => description(text),
+            Condition::Tag(tag) => todo.tags.iter().flatten().any(|t| t.to_lowercase() == *tag),
+            Condition::Priority(comparison, priority) => {
+                comparison.holds(priority.cmp(&todo.priority))
+            }
+            Condition::Status(status) => todo.status == *status,
+            Condition::Date(field, comparison, value) => {
+                let date = field.of(todo);
+                match value {
+                    DateValue::Any => date.is_some(),
+                    DateValue::Range(range) => range.matches(date, now, zone),
+                    DateValue::Date(bound) => date.is_some_and(|date| match comparison {
+                        Comparison::Eq => date.date(zone) == bound.date(zone),
+                        Comparison::Lt => date.is_before(*bound, zone),
+                        Comparison::Le => !date.is_after(*bound, zone),
+                        Comparison::Gt => date.is_after(*bound, zone),
+                        Comparison::Ge => !date.is_before(*bound, zone),
+                    }),
+                }
+            }
+            Condition::Is(state) => match state {
+                State::Open => !todo.status.is_closed(),
+                State::Closed => todo.status.is_closed(),
+                State::Overdue => todo
+                    .due_date
+                    .is_some_and(|due| due.is_overdue_at(now, zone)),
+                State::Deferred => todo
+                    .wait_until
+                    .is_some_and(|wait| wait.is_future_at(now, zone)),
+                State::Recurring => todo.recurrence.is_some(),
+            },
+        }
+    }
+}
+
+impl Comparison {
+    /// Whether a value that compares to the bound as `ordering` passes.
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Eq => ordering == Ordering::Equal,
+            Comparison::Lt => ordering == Ordering::Less,
+            Comparison::Le => ordering != Ordering::Greater,
+            Comparison::Gt => ordering == Ordering::Greater,
+            Comparison::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+impl DateField {
+    fn of(self, todo: &Todo) -> Option<Due> {
+        match self {
+            DateField::Due => todo.due_date,
+            DateField::Scheduled => todo.scheduled,
+            DateField::Wait => todo.wait_until,
+            DateField::Created => Some(Due::At(todo.created_at)),
+            DateField::Updated => Some(Due::At(todo.updated_at)),
+            DateField::Started => todo.started_at.map(Due::At),
+            DateField::Completed => todo.completed_at.map(Due::At),
+        }
+    }
+}
+
+enum Field {
+    Title,
+    Description,
+    Tag,
+    Priority,
+    Status,
+    Is,
+    Date(DateField),
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "title" => Field::Title,
+            "description" | "desc" => Field::Description,
+            "tag" | "tags" => Field::Tag,
+            "priority" => Field::Priority,
+            "status" => Field::Status,
+            "is" => Field::Is,
+            "due" => Field::Date(DateField::Due),
+            "scheduled" => Field::Date(DateField::Scheduled),
+            "wait" | "wait-until" => Field::Date(DateField::Wait),
+            "created" => Field::Date(DateField::Created),
+            "updated" => Field::Date(DateField::Updated),
+            "started" => Field::Date(DateField::Started),
+            "completed" => Field::Date(DateField::Completed),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum TokenKind {
+    Open,
+    Close,
+    /// A word with its quotes removed. `quoted` is where in `text` the
+    /// first quoted part began; operators are only looked for before it.
+    Word {
+        text: String,
+        quoted: Option<usize>,
+    },
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    /// Byte offset in the filter, for error messages.
+    start: usize,
+}
+
+fn tokenize(input: &str) -> TodoResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let kind = match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => {
+                chars.next();
+                TokenKind::Open
+            }
+            ')' => {
+                chars.next();
+                TokenKind::Close
+            }
+            _ => {
+                let mut text = String::new();
+                let mut quoted = None;
+                while let Some(&(at, c)) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c != '"' {
+                        text.push(c);
+                        continue;
+                    }
+                    quoted.get_or_insert(text.len());
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, c)) => text.push(c),
+                            None => {
+                                return Err(filter_error(input, at, "this quote is never closed"));
+                            }
+                        }
+                    }
+                }
+                TokenKind::Word { text, quoted }
+            }
+        };
+        tokens.push(Token { kind, start });
+    }
+    Ok(tokens)
+}
+
+fn filter_error(input: &str, position: usize, message: impl Into<String>) -> TodoError {
+    TodoError::InvalidFilter {
+        filter: input.to_string(),
+        position,
+        message: message.into(),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    next: usize,
+    format: &'a str,
+    now: DateTime<Utc>,
+    zone: Zone,
+}
+
+impl Parser<'_> {
+    fn error(&self, position: usize, message: impl Into<String>) -> TodoError {
+        filter_error(self.input, position, message)
+    }
+
+    /// Consumes the next token if it is the unquoted keyword `word`.
+    fn keyword(&mut self, word: &str) -> bool {
+        let found = matches!(
+            self.tokens.get(self.next),
+            Some(Token { kind: TokenKind::Word { text, quoted: None }, .. })
+                if text.eq_ignore_ascii_case(word)
+        );
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> TodoResult<Filter> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            left = Filter::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> TodoResult<Filter> {
+        let mut left = self.not()?;
+        loop {
+            let joined = self.keyword("and")
+                || match self.tokens.get(self.next) {
+                    None
+                    | Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => false,
+                    Some(Token {
+                        kind: TokenKind::Word { text, quoted: None },
+                        ..
+                    }) => !text.eq_ignore_ascii_case("or"),
+                    Some(_) => true,
+                };
+            if !joined {
+                return Ok(left);
+            }
+            left = Filter::And(Box::new(left), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> TodoResult<Filter> {
+        if self.keyword("not") {
+            return Ok(Filter::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> TodoResult<Filter> {
+        let Some(token) = self.tokens.get(self.next) else {
+            let message = match self.next.checked_sub(1).map(|i| &self.tokens[i].kind) {
+                Some(TokenKind::Word { text, .. }) => {
+                    format!("expected a condition after `{text}`")
+                }
+                _ => "expected a condition".into(),
+            };
+            return Err(self.error(self.input.len(), message));
+        };
+        let start = token.start;
+        match &token.kind {
+            TokenKind::Open => {
+                self.next += 1;
+                let inner = self.or()?;
+                match self.tokens.get(self.next) {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => {
+                        self.next += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(self.error(start, "this `(` is never closed")),
+                }
+            }
+            TokenKind::Close => Err(self.error(start, "expected a condition before `)`")),
+            TokenKind::Word { text, quoted: None }
+                if ["and", "or"].iter().any(|k| text.eq_ignore_ascii_case(k)) =>
+            {
+                Err(self.error(start, format!("expected a condition before `{text}`")))
+            }
+            TokenKind::Word { text, quoted } => {
+                let (text, quoted) = (text.clone(), *quoted);
+                self.next += 1;
+                self.condition(&text, quoted, start)
+            }
+        }
+    }
+
+    /// Parses one `field:value` word, or plain text.
+    fn condition(&self, word: &str, quoted: Option<usize>, start: usize) -> TodoResult<Filter> {
+        let searched = &word[..quoted.unwrap_or(word.len())];
+        let Some(op_at) = searched.find([':', '=', '!', '<', '>']) else {
+            return Ok(Filter::Condition(Condition::Text(word.to_lowercase())));
+        };
+        let (name, rest) = word.split_at(op_at);
+        let (comparison, op) = [
+            (Comparison::Le, "<="),
+            (Comparison::Ge, ">="),
+            (Comparison::Eq, "!="),
+            (Comparison::Eq, ":"),
+            (Comparison::Eq, "="),
+            (Comparison::Lt, "<"),
+            (Comparison::Gt, ">"),
+        ]
+        .into_iter()
+        .find(|(_, op)| rest.starts_with(op))
+        .ok_or_else(|| self.error(start + op_at, "expected `!=` after the field name"))?;
+        let value = &rest[op.len()..];
+        let value_at = start + op_at + op.len();
+
+        if name.is_empty() {
+            return Err(self.error(start, format!("expected a field name before `{op}`")));
+        }
+        let field = Field::parse(name).ok_or_else(|| {
+            self.error(
+                start,
+                format!("unknown field `{name}`, expected one of {FIELDS}"),
+            )
+        })?;
+        if value.is_empty() && quoted.is_none() {
+            return Err(self.error(value_at, format!("expected a value after `{name}{op}`")));
+        }
+        if comparison != Comparison::Eq && !matches!(field, Field::Priority | Field::Date(_)) {
+            return Err(self.error(
+                start + op_at,
+                format!("`{name}` cannot be compared with `{op}`, use `:` or `!=`"),
+            ));
+        }
+
+        let condition = match field {
+            Field::Title => Condition::Title(value.to_lowercase()),
+            Field::Description => Condition::Description(value.to_lowercase()),
+            Field::Tag => Condition::Tag(value.to_lowercase()),
+            Field::Priority => Condition::Priority(
+                comparison,
+                Priority::from_str(value, true).map_err(|_| {
+                    self.error(
+                        value_at,
+                        format!("unknown priority `{value}`, expected high, medium or low"),
+                    )
+                })?,
+            ),
+            Field::Status => Condition::Status(
+                value
+                    .parse::<Status>()
+                    .map_err(TodoError::UnknownStatus)
+                    .and_then(Status::validate)
+                    .map_err(|error| self.error(value_at, error.to_string()))?,
+            ),
+            Field::Is => Condition::Is(State::from_str(value, true).map_err(|_| {
+                self.error(
+                    value_at,
+                    format!(
+                        "unknown state `{value}`, expected open, closed, overdue, deferred \
+                         or recurring"
+                    ),
+                )
+            })?),
+            Field::Date(field) => {
+                Condition::Date(field, comparison, self.date(value, comparison, value_at)?)
+            }
+        };
+        let filter = Filter::Condition(condition);
+        Ok(if op == "!=" {
+            Filter::Not(Box::new(filter))
+        } else {
+            filter
+        })
+    }
+
+    /// A date, `+3d`-style offset from now, or with `:` also `any` or a
+    /// [`DueFilter`] name such as `today` or `none`.
+    fn date(&self, value: &str, comparison: Comparison, at: usize) -> TodoResult<DateValue> {
+        if comparison == Comparison::Eq {
+            if value.eq_ignore_ascii_case("any") {
+                return Ok(DateValue::Any);
+            }
+            if let Ok(range) = DueFilter::from_str(value, true) {
+                return Ok(DateValue::Range(range));
+            }
+        }
+        if let Some(due) = offset(value, self.now, self.zone) {
+            return Ok(DateValue::Date(due));
+        }
+        parse_due_at(value, self.format, self.now, self.zone)
+            .map(DateValue::Date)
+            .map_err(|error| self.error(at, error.to_string()))
+    }
+}
+
+/// `+3d`, `-2w`: hours (`h`), days (`d`), weeks (`w`), months (`m`) or
+/// years (`y`) from `now`. Anything longer than hours is a whole day.
+fn offset(value: &str, now: DateTime<Utc>, zone: Zone) -> Option<Due> {
+    if !value.starts_with(['+', '-']) {
+        return None;
+    }
+    let unit = value.chars().last()?;
+    let count: i64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    let today = zone.local(now).date();
+    let months = |months: i64| {
+        let step = Months::new(months.unsigned_abs().try_into().ok()?);
+        if months < 0 {
+            today.checked_sub_months(step)
+        } else {
+            today.checked_add_months(step)
+        }
+    };
+    Some(match unit {
+        'h' => Due::At(now.checked_add_signed(Duration::try_hours(count)?)?),
+        'd' => Due::Date(today.checked_add_signed(Duration::try_days(count)?)?),
+        'w' => Due::Date(today.checked_add_signed(Duration::try_weeks(count)?)?),
+        'm' => Due::Date(months(count)?),
+        'y' => Due::Date(months(count.checked_mul(12)?)?),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::AddTodoInput;
+    use chrono::{NaiveDate, TimeZone};
+    use chrono_tz::Tz;
+
+    const ZONE: Zone = Zone::Named(Tz::UTC);
+
+    fn now() -> DateTime<Utc> {
+        // A Thursday.
+        Utc.with_ymd_and_hms(2025, 10, 2, 12, 0, 0).unwrap()
+    }
+
+    fn parse(input: &str) -> TodoResult<Filter> {
+        Filter::parse_at(input, "%Y-%m-%d", now(), ZONE)
+    }
+
+    fn todo(title: &str, priority: Priority, tags: &[&str], due_day: Option<u32>) -> Todo {
+        Todo::new(AddTodoInput {
+            priority,
+            due_date: due_day.map(|d| Due::Date(NaiveDate::from_ymd_opt(2025, 10, d).unwrap())),
+            tags: Some(tags.iter().map(|&t| t.into()).collect()),
+            ..AddTodoInput::new(title)
+        })
+    }
+
+    fn matching<'a>(filter: &str, todos: &'a [Todo]) -> Vec<&'a str> {
+        let filter = parse(filter).unwrap();
+        todos
+            .iter()
+            .filter(|todo| filter.matches_at(todo, now(), ZONE))
+            .map(|todo| todo.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn evaluates_nested_expressions() {
+        let mut done = todo("Done", Priority::High, &["work"], Some(3));
+        done.set_status(Status::Done);
+        let todos = [
+            todo("Report", Priority::High, &["work"], Some(4)),
+            todo("Hotfix", Priority::High, &["Urgent"], Some(3)),
+            todo("Taxes", Priority::High, &["home"], Some(3)),
+            todo("Later", Priority::High, &["work"], Some(20)),
+            todo("Slides", Priority::Low, &["work"], Some(3)),
+            done,
+        ];
+        assert_eq!(
+            matching(
+                "priority:high and (tag:work or tag:urgent) and not status:done and due<+3d",
+                &todos
+            ),
+            ["Report", "Hotfix"]
+        );
+        assert_eq!(
+            matching("tag:work priority>=medium due!=2025-10-04", &todos),
+            ["Later", "Done"]
+        );
+        assert_eq!(
+            matching("priority<high or is:closed", &todos),
+            ["Slides", "Done"]
+        );
+        assert_eq!(
+            matching(r#"title:"hot" OR taxes"#, &todos),
+            ["Hotfix", "Taxes"]
+        );
+        assert!(matching("due:none or is:recurring", &todos).is_empty());
+    }
+
+    #[test]
+    fn binds_not_then_and_then_or() {
+        let text = |t: &str| Filter::Condition(Condition::Text(t.into()));
+        let and = |a, b| Filter::And(Box::new(a), Box::new(b));
+        assert_eq!(
+            parse("a or not b c").unwrap(),
+            Filter::Or(
+                Box::new(text("a")),
+                Box::new(and(Filter::Not(Box::new(text("b"))), text("c")))
+            )
+        );
+        assert_eq!(
+            parse("due>=+1w").unwrap(),
+            Filter::Condition(Condition::Date(
+                DateField::Due,
+                Comparison::Ge,
+                DateValue::Date(Due::Date(NaiveDate::from_ymd_opt(2025, 10, 9).unwrap()))
+            ))
+        );
+    }
+
+    #[test]
+    fn extracts_the_conditions_a_store_can_index() {
+        let query = parse("status:done and (priority:high and due<2025-10-05) tag:x")
+            .unwrap()
+            .query();
+        assert_eq!(query.status, Some(Status::Done));
+        assert_eq!(query.priority, Some(Priority::High));
+        assert_eq!(
+            query.due_before,
+            Some(Due::Date(NaiveDate::from_ymd_opt(2025, 10, 5).unwrap()))
+        );
+        for input in ["status:done or tag:x", "not priority:high", "priority>low"] {
+            assert_eq!(
+                parse(input).unwrap().query(),
+                TodoQuery::default(),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn points_at_parse_errors() {
+        let error = |input: &str| match parse(input).unwrap_err() {
+            TodoError::InvalidFilter {
+                position, message, ..
+            } => (position, message),
+            other => panic!("{other}"),
+        };
+        assert_eq!(error("tag:work and (due<+3d").0, 13);
+        assert_eq!(
+            error("tag:work or"),
+            (11, "expected a condition after `or`".into())
+        );
+        assert_eq!(error("a)").0, 1);
+        assert_eq!(error("").1, "the filter is empty");
+        assert_eq!(
+            error("priority:hgh"),
+            (
+                9,
+                "unknown priority `hgh`, expected high, medium or low".into()
+            )
+        );
+        assert!(error("color:red").1.starts_with("unknown field `color`"));
+        assert_eq!(error("tag<work").0, 3);
+        assert!(error("due<someday").1.contains("invalid date"));
+        assert_eq!(error(r#"title:"open"#).0, 6);
+
+        let message = parse("and x").unwrap_err().to_string();
+        assert_eq!(
+            message,
+            "invalid filter: expected a condition before `and`\n  and x\n  ^"
+        );
+    }
+}
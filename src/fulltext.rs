@@ -0,0 +1,485 @@
+use crate::todo::Todo;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+    str::FromStr,
+};
+use uuid::Uuid;
+
+/// BM25 term-frequency saturation and length normalisation.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// The parts of a todo that are searched, and how much a match in each
+/// counts towards the ranking.
+const FIELDS: [Field; 3] = [Field::Title, Field::Tags, Field::Description];
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Title,
+    Tags,
+    Description,
+}
+
+impl Field {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn weight(self) -> f64 {
+        match self {
+            Field::Title => 3.0,
+            Field::Tags => 2.0,
+            Field::Description => 1.0,
+        }
+    }
+
+    fn text(self, todo: &Todo) -> String {
+        match self {
+            Field::Title => todo.title.clone(),
+            Field::Tags => todo.tags.as_deref().unwrap_or_default().join(" "),
+            Field::Description => todo.description.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// The words of `text`, lowercased, with where they are in it. Words are
+/// runs of letters and digits; everything else separates them.
+pub fn words(text: &str) -> impl Iterator<Item = (String, Range<usize>)> + '_ {
+    word_ranges(text).map(|range| (text[range.clone()].to_lowercase(), range))
+}
+
+fn word_ranges(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = None;
+    text.char_indices()
+        .chain([(text.len(), ' ')])
+        .filter_map(move |(at, c)| {
+            if c.is_alphanumeric() {
+                start.get_or_insert(at);
+                None
+            } else {
+                start.take().map(|start| start..at)
+            }
+        })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Word(String),
+    /// `plan*`: any word starting with this.
+    Prefix(String),
+    /// `"weekly report"`: these words in a row.
+    Phrase(Vec<String>),
+}
+
+/// What to look for in a full-text search: words, `prefix*`es and
+/// `"quoted phrases"`, all of which must match. Case and punctuation are
+/// ignored, except that words joined by punctuation, like `e-mail`, are
+/// looked for as a phrase; words are matched whole, without stemming.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextQuery {
+    pub terms: Vec<Term>,
+}
+
+impl FromStr for TextQuery {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut terms = Vec::new();
+        // Odd parts were between quotes; an unclosed quote runs to the end.
+        for (i, part) in input.split('"').enumerate() {
+            if i % 2 == 1 {
+                terms.extend(Term::phrase(words(part).map(|(word, _)| word).collect()));
+                continue;
+            }
+            for piece in part.split_whitespace() {
+                let mut piece_words: Vec<String> = words(piece).map(|(word, _)| word).collect();
+                let prefix = piece.ends_with('*').then(|| piece_words.pop()).flatten();
+                terms.extend(Term::phrase(piece_words));
+                terms.extend(prefix.map(Term::Prefix));
+            }
+        }
+        if terms.is_empty() {
+            return Err("the search text has no words to look for".into());
+        }
+        Ok(Self { terms })
+    }
+}
+
+impl Term {
+    /// One word on its own, several in a row, or nothing for no words.
+    fn phrase(mut words: Vec<String>) -> Option<Self> {
+        match words.len() {
+            0 => None,
+            1 => Some(Term::Word(words.remove(0))),
+            _ => Some(Term::Phrase(words)),
+        }
+    }
+
+    fn matches(&self, word: &str) -> bool {
+        match self {
+            Term::Word(term) => word == term,
+            Term::Prefix(prefix) => word.starts_with(prefix.as_str()),
+            Term::Phrase(_) => false,
+        }
+    }
+}
+
+/// Splits `text` into pieces, flagging the ones that match a term of
+/// `query` so they can be highlighted. Adjacent matches form one piece.
+pub fn segments<'a>(text: &'a str, query: &TextQuery) -> Vec<(&'a str, bool)> {
+    let tokens: Vec<(String, Range<usize>)> = words(text).collect();
+    let mut marked = vec![false; tokens.len()];
+    for term in &query.terms {
+        if let Term::Phrase(phrase) = term {
+            for start in 0..(tokens.len() + 1).saturating_sub(phrase.len()) {
+                let window = &tokens[start..start + phrase.len()];
+                if window.iter().zip(phrase).all(|((word, _), p)| word == p) {
+                    marked[start..start + phrase.len()].fill(true);
+                }
+            }
+        } else {
+            for (mark, (word, _)) in marked.iter_mut().zip(&tokens) {
+                *mark |= term.matches(word);
+            }
+        }
+    }
+
+    let mut pieces = Vec::new();
+    let mut last = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        if !marked[i] {
+            i += 1;
+            continue;
+        }
+        let start = tokens[i].1.start;
+        while i + 1 < tokens.len() && marked[i + 1] {
+            i += 1;
+        }
+        let end = tokens[i].1.end;
+        if last < start {
+            pieces.push((&text[last..start], false));
+        }
+        pieces.push((&text[start..end], true));
+        last = end;
+        i += 1;
+    }
+    if last < text.len() {
+        pieces.push((&text[last..], false));
+    }
+    pieces
+}
+
+/// Where one word occurs in one field of one todo: its word positions
+/// are `positions[start..end]` of the word's [`Postings`].
+#[derive(Debug)]
+struct Posting {
+    doc: u32,
+    field: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Every occurrence of one word, ordered by todo and field. Positions
+/// share one buffer so indexing does not allocate per occurrence.
+#[derive(Debug, Default)]
+struct Postings {
+    entries: Vec<Posting>,
+    positions: Vec<u32>,
+}
+
+impl Postings {
+    fn add(&mut self, doc: u32, field: usize, position: u32) {
+        self.positions.push(position);
+        let end = self.positions.len();
+        match self.entries.last_mut() {
+            Some(last) if last.doc == doc && last.field == field => last.end = end,
+            _ => self.entries.push(Posting {
+                doc,
+                field,
+                start: end - 1,
+                end,
+            }),
+        }
+    }
+
+    fn find(&self, doc: u32, field: usize) -> Option<&Posting> {
+        self.entries
+            .binary_search_by_key(&(doc, field), |p| (p.doc, p.field))
+            .ok()
+            .map(|i| &self.entries[i])
+    }
+
+    fn positions(&self, posting: &Posting) -> &[u32] {
+        &self.positions[posting.start..posting.end]
+    }
+}
+
+/// An inverted index over the titles, tags and descriptions of a set of
+/// todos, ranking matches with BM25 with title matches weighing most.
+///
+/// The index is not stored: it is built for each search, which reads every
+/// todo once, at about the cost of loading them. A search then only looks
+/// at the postings of the words it asks for.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    ids: Vec<Uuid>,
+    /// Number of words in each field of each todo.
+    lengths: Vec<[u32; 3]>,
+    average_lengths: [f64; 3],
+    /// Sorted, so the words starting with a prefix are a range.
+    postings: BTreeMap<String, Postings>,
+}
+
+impl SearchIndex {
+    pub fn new<'a>(todos: impl IntoIterator<Item = &'a Todo>) -> Self {
+        let mut index = Self::default();
+        for todo in todos {
+            let doc = index.ids.len() as u32;
+            index.ids.push(todo.id);
+            let mut lengths = [0; 3];
+            for (field, length) in FIELDS.iter().zip(&mut lengths) {
+                let field_index = field.index();
+                // Lowercasing the whole field once and only allocating words
+                // not seen before keeps building cheap.
+                let text = field.text(todo).to_lowercase();
+                for (position, range) in word_ranges(&text).enumerate() {
+                    let word = &text[range];
+                    if !index.postings.contains_key(word) {
+                        index.postings.insert(word.to_string(), Postings::default());
+                    }
+                    let postings = index.postings.get_mut(word).expect("just inserted");
+                    postings.add(doc, field_index, position as u32);
+                    *length += 1;
+                }
+            }
+            index.lengths.push(lengths);
+        }
+        let count = index.ids.len().max(1) as f64;
+        for (i, average) in index.average_lengths.iter_mut().enumerate() {
+            *average = index.lengths.iter().map(|l| f64::from(l[i])).sum::<f64>() / count;
+        }
+        index
+    }
+
+    /// The todos matching every term of `query` with their scores, best
+    /// match first.
+    pub fn search(&self, query: &TextQuery) -> Vec<(Uuid, f64)> {
+        let mut scores: Option<HashMap<u32, f64>> = None;
+        for term in &query.terms {
+            let counts = self.counts(term);
+            let idf = self.idf(counts.len());
+            let term_scores = counts
+                .into_iter()
+                .map(|(doc, counts)| (doc, idf * self.weigh(doc, counts)));
+            scores = Some(match scores {
+                None => term_scores.collect(),
+                Some(scores) => term_scores
+                    .filter_map(|(doc, score)| scores.get(&doc).map(|sum| (doc, sum + score)))
+                    .collect(),
+            });
+        }
+        let mut ranked: Vec<(Uuid, f64)> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(doc, score)| (self.ids[doc as usize], score))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+
+    /// How often `term` occurs in each field of each todo containing it.
+    fn counts(&self, term: &Term) -> HashMap<u32, [u32; 3]> {
+        let mut counts: HashMap<u32, [u32; 3]> = HashMap::new();
+        let mut add = |doc: u32, field: usize, n: usize| {
+            counts.entry(doc).or_default()[field] += n as u32;
+        };
+        match term {
+            Term::Word(word) => {
+                for posting in self.postings.get(word).iter().flat_map(|p| &p.entries) {
+                    add(posting.doc, posting.field, posting.end - posting.start);
+                }
+            }
+            Term::Prefix(prefix) => {
+                let words = self
+                    .postings
+                    .range(prefix.clone()..)
+                    .take_while(|(word, _)| word.starts_with(prefix.as_str()));
+                for posting in words.flat_map(|(_, postings)| &postings.entries) {
+                    add(posting.doc, posting.field, posting.end - posting.start);
+                }
+            }
+            Term::Phrase(phrase) => {
+                let (Some(first), Some(rest)) = (
+                    self.postings.get(&phrase[0]),
+                    phrase[1..]
+                        .iter()
+                        .map(|word| self.postings.get(word))
+                        .collect::<Option<Vec<&Postings>>>(),
+                ) else {
+                    return counts;
+                };
+                for posting in &first.entries {
+                    let Some(following) = rest
+                        .iter()
+                        .map(|postings| {
+                            let posting = postings.find(posting.doc, posting.field)?;
+                            Some(postings.positions(posting))
+                        })
+                        .collect::<Option<Vec<&[u32]>>>()
+                    else {
+                        continue;
+                    };
+                    let n = first
+                        .positions(posting)
+                        .iter()
+                        .filter(|&&start| {
+                            following.iter().zip(1..).all(|(positions, offset)| {
+                                positions.binary_search(&(start + offset)).is_ok()
+                            })
+                        })
+                        .count();
+                    if n > 0 {
+                        add(posting.doc, posting.field, n);
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    /// Rarer terms count more.
+    fn idf(&self, matching: usize) -> f64 {
+        let (total, matching) = (self.ids.len() as f64, matching as f64);
+        (1.0 + (total - matching + 0.5) / (matching + 0.5)).ln()
+    }
+
+    /// BM25 term weight, summed over the weighted fields.
+    fn weigh(&self, doc: u32, counts: [u32; 3]) -> f64 {
+        FIELDS
+            .iter()
+            .filter(|field| counts[field.index()] > 0)
+            .map(|field| {
+                let i = field.index();
+                let frequency = f64::from(counts[i]);
+                let length = f64::from(self.lengths[doc as usize][i]);
+                let norm = 1.0 - B + B * length / self.average_lengths[i].max(1.0);
+                field.weight() * frequency * (K1 + 1.0) / (frequency + K1 * norm)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::AddTodoInput;
+
+    fn todo(title: &str, description: &str, tags: &[&str]) -> Todo {
+        Todo::new(AddTodoInput {
+            description: Some(description.into()),
+            tags: Some(tags.iter().map(|&t| t.into()).collect()),
+            ..AddTodoInput::new(title)
+        })
+    }
+
+    fn search<'a>(todos: &'a [Todo], query: &str) -> Vec<&'a str> {
+        let index = SearchIndex::new(todos);
+        index
+            .search(&query.parse().unwrap())
+            .into_iter()
+            .map(|(id, _)| todos.iter().find(|t| t.id == id).unwrap().title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parses_words_prefixes_and_phrases() {
+        let query: TextQuery = r#"Plan* "weekly  report" e-mail"#.parse().unwrap();
+        assert_eq!(
+            query.terms,
+            [
+                Term::Prefix("plan".into()),
+                Term::Phrase(vec!["weekly".into(), "report".into()]),
+                Term::Phrase(vec!["e".into(), "mail".into()]),
+            ]
+        );
+        assert_eq!(
+            "re-sched*".parse::<TextQuery>().unwrap().terms,
+            [Term::Word("re".into()), Term::Prefix("sched".into())]
+        );
+        assert!(" * \"\" ".parse::<TextQuery>().is_err());
+    }
+
+    #[test]
+    fn ranks_matches_by_relevance() {
+        let todos = [
+            todo("Buy groceries", "milk, eggs and bread", &["home"]),
+            todo(
+                "Write the weekly report",
+                "Send the report to the team",
+                &["work"],
+            ),
+            todo("Plan the week", "Report on last week's plans", &[]),
+            todo("Call mom", "", &["family"]),
+        ];
+        // Title matches outrank description matches.
+        assert_eq!(
+            search(&todos, "report"),
+            ["Write the weekly report", "Plan the week"]
+        );
+        assert_eq!(search(&todos, "REPORT team"), ["Write the weekly report"]);
+        assert_eq!(
+            search(&todos, "\"weekly report\""),
+            ["Write the weekly report"]
+        );
+        assert!(search(&todos, "\"report weekly\"").is_empty());
+        assert_eq!(search(&todos, "plan*"), ["Plan the week"]);
+        assert_eq!(search(&todos, "family"), ["Call mom"]);
+        assert!(search(&todos, "repo").is_empty(), "words match whole");
+        assert!(search(&todos, "week-report").is_empty());
+        assert_eq!(search(&todos, "weekly-report"), ["Write the weekly report"]);
+    }
+
+    #[test]
+    fn searches_tens_of_thousands_of_todos() {
+        let words = [
+            "call", "email", "review", "report", "plan", "team", "budget",
+        ];
+        let mut todos: Vec<Todo> = (0..30_000)
+            .map(|i| {
+                let word = |n: usize| words[(i * n + n) % words.len()];
+                let title = format!("{} the {} {i}", word(1), word(2));
+                let description = format!("{} {} for the {}", word(3), word(4), word(5));
+                todo(&title, &description, &[word(6)])
+            })
+            .collect();
+        todos.push(todo("Renew the passport", "", &["travel"]));
+
+        let index = SearchIndex::new(&todos);
+        let found = index.search(&"passport renew*".parse().unwrap());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, todos[30_000].id);
+        assert!(
+            index
+                .search(&"\"review the\" budget".parse().unwrap())
+                .len()
+                > 1000
+        );
+    }
+
+    #[test]
+    fn flags_matching_pieces() {
+        let query: TextQuery = r#"milk "fresh bread" egg*"#.parse().unwrap();
+        assert_eq!(
+            segments("Milk, eggs and fresh bread.", &query),
+            [
+                ("Milk, eggs", true),
+                (" and ", false),
+                ("fresh bread", true),
+                (".", false),
+            ]
+        );
+        assert_eq!(segments("nothing", &query), [("nothing", false)]);
+    }
+}
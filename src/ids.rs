@@ -0,0 +1,209 @@
+use crate::{
+    error::{TodoError, TodoResult},
+    todo::Todo,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Prefixes are never shown shorter than this, even when fewer characters
+/// would already be unique.
+const MIN_PREFIX_LEN: usize = 4;
+
+/// Short ways to refer to the todos in a list: a number per todo, counted
+/// in creation order so sorting and filtering never change it, and the
+/// shortest prefix of its UUID that no other todo shares.
+#[derive(Default)]
+pub struct IdIndex {
+    /// Titles, to tell apart the todos an ambiguous prefix matches.
+    titles: HashMap<Uuid, String>,
+    /// IDs in creation order; the number of `ordered[i]` is `i + 1`.
+    ordered: Vec<Uuid>,
+    numbers: HashMap<Uuid, usize>,
+    prefix_lens: HashMap<Uuid, usize>,
+}
+
+impl IdIndex {
+    pub fn new(todos: &HashMap<Uuid, Todo>) -> Self {
+        Self::from_entries(
+            todos
+                .values()
+                .map(|todo| (todo.id, todo.created_at, todo.title.clone())),
+        )
+    }
+
+    /// Builds the index from the ID, creation time and title of every todo,
+    /// for stores that can list those without loading whole todos.
+    pub fn from_entries(entries: impl IntoIterator<Item = (Uuid, DateTime<Utc>, String)>) -> Self {
+        let mut created = Vec::new();
+        let mut titles = HashMap::new();
+        for (id, created_at, title) in entries {
+            created.push((created_at, id));
+            titles.insert(id, title);
+        }
+        created.sort();
+        let ordered: Vec<Uuid> = created.into_iter().map(|(_, id)| id).collect();
+        let numbers = ordered
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i + 1))
+            .collect();
+
+        let mut texts: Vec<(String, Uuid)> =
+            ordered.iter().map(|id| (id.to_string(), *id)).collect();
+        texts.sort();
+        let prefix_lens = texts
+            .iter()
+            .enumerate()
+            .map(|(i, (text, id))| {
+                let before = i
+                    .checked_sub(1)
+                    .map_or(0, |j| common_len(text, &texts[j].0));
+                let after = texts.get(i + 1).map_or(0, |next| common_len(text, &next.0));
+                let mut len = (before.max(after) + 1).max(MIN_PREFIX_LEN);
+                // An all-digit prefix would be read back as a number.
+                while len < text.len() && text[..len].bytes().all(|b| b.is_ascii_digit()) {
+                    len += 1;
+                }
+                (*id, len.min(text.len()))
+            })
+            .collect();
+
+        Self {
+            titles,
+            ordered,
+            numbers,
+            prefix_lens,
+        }
+    }
+
+    /// The todo's number, starting at 1.
+    pub fn number(&self, id: Uuid) -> Option<usize> {
+        self.numbers.get(&id).copied()
+    }
+
+    /// Shortest unique prefix of the todo's UUID, or the full UUID for IDs
+    /// that are not in the list.
+    pub fn short_id(&self, id: Uuid) -> String {
+        let text = id.to_string();
+        match self.prefix_lens.get(&id) {
+            Some(&len) => text[..len].to_string(),
+            None => text,
+        }
+    }
+
+    /// Resolves a full UUID, a todo number or an unambiguous UUID prefix.
+    /// Full UUIDs are returned as-is, whether or not the todo exists.
+    pub fn resolve(&self, input: &str) -> TodoResult<Uuid> {
+        let input = input.trim();
+        if let Ok(id) = Uuid::parse_str(input) {
+            return Ok(id);
+        }
+        if input.bytes().all(|b| b.is_ascii_digit())
+            && let Some(&id) = input
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| self.ordered.get(n.checked_sub(1)?))
+        {
+            return Ok(id);
+        }
+
+        let prefix = input.to_ascii_lowercase();
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Err(TodoError::InvalidId(input.to_string()));
+        }
+        let matches: Vec<Uuid> = self
+            .ordered
+            .iter()
+            .copied()
+            .filter(|id| id.to_string().starts_with(&prefix))
+            .collect();
+        match matches[..] {
+            [] => Err(TodoError::UnknownId(input.to_string())),
+            [id] => Ok(id),
+            _ => Err(TodoError::AmbiguousId {
+                input: input.to_string(),
+                candidates: matches
+                    .iter()
+                    .map(|id| (*id, self.titles[id].clone()))
+                    .collect(),
+            }),
+        }
+    }
+
+    pub fn resolve_all<S: AsRef<str>>(&self, inputs: &[S]) -> TodoResult<Vec<Uuid>> {
+        inputs
+            .iter()
+            .map(|input| self.resolve(input.as_ref()))
+            .collect()
+    }
+}
+
+fn common_len(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::AddTodoInput;
+    use chrono::Duration;
+
+    fn todos(ids: &[&str]) -> HashMap<Uuid, Todo> {
+        ids.iter()
+            .enumerate()
+            .map(|(i, id)| {
+                let mut todo = Todo::new(AddTodoInput::new(format!("Todo {i}")));
+                todo.id = Uuid::parse_str(id).unwrap();
+                todo.created_at += Duration::seconds(i as i64);
+                (todo.id, todo)
+            })
+            .collect()
+    }
+
+    const A: &str = "abcd1234-0000-4000-8000-000000000001";
+    const B: &str = "abcd9876-0000-4000-8000-000000000002";
+    const C: &str = "12345678-0000-4000-8000-000000000003";
+
+    #[test]
+    fn resolves_numbers_prefixes_and_full_ids() {
+        let todos = todos(&[A, B, C]);
+        let index = IdIndex::new(&todos);
+        let [a, b, c] = [A, B, C].map(|id| Uuid::parse_str(id).unwrap());
+
+        assert_eq!(index.resolve("1").unwrap(), a);
+        assert_eq!(index.resolve("3").unwrap(), c);
+        assert_eq!(index.resolve("ABCD9").unwrap(), b);
+        assert_eq!(index.resolve("1234").unwrap(), c);
+        assert_eq!(index.resolve(A).unwrap(), a);
+        assert_eq!(index.number(b), Some(2));
+    }
+
+    #[test]
+    fn reports_ambiguous_unknown_and_invalid_ids() {
+        let todos = todos(&[A, B]);
+        let index = IdIndex::new(&todos);
+
+        match index.resolve("abcd") {
+            Err(TodoError::AmbiguousId { candidates, .. }) => assert_eq!(candidates.len(), 2),
+            other => panic!("expected an ambiguity error, got {other:?}"),
+        }
+        assert!(matches!(
+            index.resolve("ffff"),
+            Err(TodoError::UnknownId(_))
+        ));
+        assert!(matches!(
+            index.resolve("milk"),
+            Err(TodoError::InvalidId(_))
+        ));
+    }
+
+    #[test]
+    fn short_ids_are_unique_and_not_numeric() {
+        let todos = todos(&[A, B, C]);
+        let index = IdIndex::new(&todos);
+        assert_eq!(index.short_id(Uuid::parse_str(A).unwrap()), "abcd1");
+        assert_eq!(index.short_id(Uuid::parse_str(B).unwrap()), "abcd9");
+        assert_eq!(index.short_id(Uuid::parse_str(C).unwrap()), "12345678-");
+    }
+}
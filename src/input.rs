@@ -1,7 +1,14 @@
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use crate::{priority::Priority, recurrence::Recurrence, status::Status};
+use crate::{
+    due::{Due, DueFilter},
+    filter::Filter,
+    fulltext::TextQuery,
+    priority::Priority,
+    recurrence::Recurrence,
+    status::Status,
+};
 
 #[derive(Debug, Clone)]
 pub struct AddTodoInput {
@@ -9,13 +16,35 @@ pub struct AddTodoInput {
     pub description: Option<String>,
     pub priority: Priority,
     pub status: Status,
-    pub due_date: Option<DateTime<Utc>>,
+    pub due_date: Option<Due>,
+    pub scheduled: Option<Due>,
+    pub wait_until: Option<Due>,
     pub tags: Option<Vec<String>>,
     pub recurrence: Option<Recurrence>,
     pub parent_id: Option<Uuid>,
     pub subtasks: Option<Vec<Uuid>>,
 }
 
+impl AddTodoInput {
+    /// A pending, medium-priority todo with nothing but a title; set other
+    /// fields with `..AddTodoInput::new(title)`.
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: None,
+            priority: Priority::Medium,
+            status: Status::Pending,
+            due_date: None,
+            scheduled: None,
+            wait_until: None,
+            tags: None,
+            recurrence: None,
+            parent_id: None,
+            subtasks: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UpdateTodoInput {
     pub id: Uuid,
@@ -23,21 +52,37 @@ pub struct UpdateTodoInput {
     pub new_description: Option<String>,
     pub new_priority: Option<Priority>,
     pub new_status: Option<Status>,
-    pub new_due_date: Option<DateTime<Utc>>,
+    pub new_due_date: Option<Due>,
+    pub new_scheduled: Option<Due>,
+    pub new_wait_until: Option<Due>,
     pub new_recurrence: Option<Recurrence>,
     pub new_tags: Option<Vec<String>>,
     pub new_parent_id: Option<Uuid>,
     pub new_subtasks: Option<Vec<Uuid>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct SearchTodoInput {
     pub id: Option<String>,
     pub title: Option<String>,
     pub priority: Option<Priority>,
     pub status: Option<Status>,
-    pub due_date: Option<DateTime<Utc>>,
+    pub due_date: Option<Due>,
+    /// Due before this day, or before this moment if it has a time.
+    pub due_before: Option<Due>,
+    /// Due after this day, or after this moment if it has a time.
+    pub due_after: Option<Due>,
+    pub due: Option<DueFilter>,
     pub recurrence: Option<Recurrence>,
     pub tags: Option<Vec<String>>,
     pub parent_id: Option<Uuid>,
+    pub created_since: Option<DateTime<Utc>>,
+    pub updated_since: Option<DateTime<Utc>>,
+    pub started_since: Option<DateTime<Utc>>,
+    pub completed_since: Option<DateTime<Utc>>,
+    /// Any occurrence of a recurring todo; matches its whole series.
+    pub series: Option<String>,
+    pub filter: Option<Filter>,
+    /// Full-text query; results are ranked by relevance when set.
+    pub text: Option<TextQuery>,
 }
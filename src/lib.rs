@@ -1,10 +1,29 @@
+pub mod cli;
+pub mod config;
+pub mod dates;
+pub mod due;
+pub mod error;
+pub mod filter;
+pub mod fulltext;
+pub mod ids;
 pub mod input;
+pub mod logging;
+pub mod menu;
+pub mod notify;
+pub mod output;
 pub mod priority;
 pub mod recurrence;
+pub mod schema;
+pub mod series;
+pub mod sortby;
+pub mod sqlite_store;
 pub mod status;
 pub mod storage;
+pub mod table;
 pub mod todo;
+pub mod todo_cli;
 pub mod utils;
+use crate::error::{TodoError, TodoResult};
 use crate::status::Status;
 use crate::todo::Todo;
 use crate::{input::AddTodoInput, priority::Priority};
@@ -17,21 +36,19 @@ pub fn add_todo_to_map(
     description: String,
     priority: Priority,
     status: Status,
-) -> Uuid {
+) -> TodoResult<Uuid> {
     let todo = Todo::new(AddTodoInput {
-        title,
         description: Some(description),
         priority,
         status,
-        due_date: None,
-        tags: None,
-        recurrence: None,
-        parent_id: None,
-        subtasks: None,
+        ..AddTodoInput::new(title)
     });
     let id = todo.id;
+    if todos.contains_key(&id) {
+        return Err(TodoError::AlreadyExists(id));
+    }
     todos.insert(id, todo);
-    id
+    Ok(id)
 }
 
 pub fn update_todo_in_map(
@@ -41,28 +58,34 @@ pub fn update_todo_in_map(
     new_description: Option<String>,
     new_priority: Option<Priority>,
     new_status: Option<Status>,
-) -> bool {
-    if let Some(todo) = todos.get_mut(&id) {
-        if let Some(title) = new_title {
-            todo.title = title;
-        }
-        if let Some(description) = new_description {
-            todo.description = Some(description);
-        }
-        if let Some(priority) = new_priority {
-            todo.priority = priority;
-        }
-        if let Some(status) = new_status {
-            todo.status = status;
-        }
-        true
-    } else {
-        false
+) -> TodoResult<()> {
+    let todo = todos.get_mut(&id).ok_or(TodoError::NotFound(id))?;
+    if let Some(status) = &new_status {
+        todo.status.check_transition(status)?;
+    }
+    let original = todo.clone();
+    if let Some(title) = new_title {
+        todo.title = title;
+    }
+    if let Some(description) = new_description {
+        todo.description = Some(description);
+    }
+    if let Some(priority) = new_priority {
+        todo.priority = priority;
+    }
+    let status_changed = new_status.is_some_and(|status| todo.set_status(status));
+    if *todo != original {
+        todo.touch();
     }
+    if status_changed {
+        series::spawn_next(todos, id);
+    }
+    Ok(())
 }
 
-pub fn delete_todo_by_id(todos: &mut HashMap<Uuid, Todo>, id: Uuid) -> bool {
-    todos.remove(&id).is_some()
+/// Removes the todo with `id` and returns it.
+pub fn delete_todo_by_id(todos: &mut HashMap<Uuid, Todo>, id: Uuid) -> TodoResult<Todo> {
+    todos.remove(&id).ok_or(TodoError::NotFound(id))
 }
 
 #[cfg(test)]
@@ -78,7 +101,8 @@ mod tests {
             "Finish the Rust book".into(),
             Priority::High,
             Status::Pending,
-        );
+        )
+        .unwrap();
 
         assert!(todos.contains_key(&id));
         assert_eq!(todos[&id].title, "Learn Rust");
@@ -94,7 +118,8 @@ mod tests {
             "Old description".into(),
             Priority::Low,
             Status::Pending,
-        );
+        )
+        .unwrap();
 
         let updated = update_todo_in_map(
             &mut todos,
@@ -105,7 +130,7 @@ mod tests {
             Some(Status::Done),
         );
 
-        assert!(updated);
+        assert!(updated.is_ok());
         let todo = &todos[&id];
         assert_eq!(todo.title, "New Title");
         assert_eq!(todo.description.as_deref(), Some("New Desc"));
@@ -113,6 +138,29 @@ mod tests {
         assert_eq!(todo.status, Status::Done);
     }
 
+    #[test]
+    fn update_todo_touches_only_when_something_changes() {
+        let mut todos = HashMap::new();
+        let id = add_todo_to_map(
+            &mut todos,
+            "Title".into(),
+            "Description".into(),
+            Priority::Low,
+            Status::Pending,
+        )
+        .unwrap();
+        let earlier = todos[&id].created_at - chrono::Duration::hours(1);
+        todos.get_mut(&id).unwrap().updated_at = earlier;
+
+        update_todo_in_map(&mut todos, id, Some("Title".into()), None, None, None).unwrap();
+        assert_eq!(todos[&id].updated_at, earlier);
+
+        update_todo_in_map(&mut todos, id, None, None, None, Some(Status::Done)).unwrap();
+        let todo = &todos[&id];
+        assert!(todo.updated_at > earlier);
+        assert!(todo.completed_at.is_some());
+    }
+
     #[test]
     fn delete_todo_removes_item() {
         let mut todos = HashMap::new();
@@ -122,16 +170,17 @@ mod tests {
             "To be deleted".into(),
             Priority::Medium,
             Status::Pending,
-        );
+        )
+        .unwrap();
 
         let deleted = delete_todo_by_id(&mut todos, id);
 
-        assert!(deleted);
+        assert_eq!(deleted.unwrap().title, "Temporary");
         assert!(!todos.contains_key(&id));
     }
 
     #[test]
-    fn update_todo_nonexistent_returns_false() {
+    fn update_todo_nonexistent_returns_not_found() {
         let mut todos = HashMap::new();
         let random_id = Uuid::new_v4();
 
@@ -144,6 +193,6 @@ mod tests {
             None,
         );
 
-        assert!(!updated);
+        assert!(matches!(updated, Err(TodoError::NotFound(id)) if id == random_id));
     }
 }
@@ -0,0 +1,62 @@
+use colored::*;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Writes diagnostics to stderr so stdout only carries command results.
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        let message = match record.level() {
+            Level::Error => message.red().bold(),
+            Level::Warn => message.yellow().bold(),
+            Level::Info => message.normal(),
+            Level::Debug | Level::Trace => message.dimmed(),
+        };
+        eprintln!("{message}");
+    }
+
+    fn flush(&self) {}
+}
+
+/// Maps `-q` and the number of `-v` flags to a log level. Warnings are
+/// shown by default; `-v` adds progress messages and `-vv` debug details.
+pub fn level_filter(quiet: bool, verbose: u8) -> LevelFilter {
+    if quiet {
+        return LevelFilter::Error;
+    }
+    match verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Installs the stderr logger. Only the first call has an effect.
+pub fn init(level: LevelFilter) {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_wins_over_verbose() {
+        assert_eq!(level_filter(false, 0), LevelFilter::Warn);
+        assert_eq!(level_filter(false, 2), LevelFilter::Debug);
+        assert_eq!(level_filter(true, 2), LevelFilter::Error);
+    }
+}
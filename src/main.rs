@@ -1,43 +1,80 @@
-use std::io;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
-use chrono::{DateTime, NaiveDate, Utc};
 use clap::{CommandFactory, Parser};
 use clap_complete::{
     generate,
     shells::{Bash, Fish, PowerShell, Zsh},
 };
-mod cli;
-mod input;
-mod menu;
-mod notify;
-mod priority;
-mod recurrence;
-mod sortby;
-mod status;
-mod storage;
-mod todo;
-mod todo_cli;
-mod utils;
 use colored::*;
-use menu::display_menu;
-use uuid::Uuid;
-
-use crate::{
-    cli::{Cli, Commands, Shell},
-    input::UpdateTodoInput,
-};
-use crate::{
-    input::AddTodoInput,
-    todo_cli::{add_todo_cli, delete_todo_cli, list_todos_cli, search_todo_cli, update_todo_cli},
+use todoscope::{
+    cli::{Backend, Cli, Commands, ConfigAction, Shell},
+    config::{self, ColorMode, Config},
+    dates::{parse_date, parse_due},
+    error::{TodoError, TodoResult},
+    filter::Filter,
+    input::{self, AddTodoInput, UpdateTodoInput},
+    logging,
+    menu::display_menu,
+    notify,
+    output::{OutputFormat, OutputOptions},
+    sqlite_store::SqliteStore,
+    status::Status,
+    storage::{JsonFileStore, TodoStore, default_data_path},
+    table::Column,
+    todo::Todo,
+    todo_cli::{
+        add_todo_cli, delete_todo_cli, list_todos_cli, search_todo_cli, set_status_cli,
+        update_todo_cli,
+    },
 };
 
 fn main() {
-    let file_path = "todos.json";
+    if let Err(error) = run() {
+        eprintln!("{}", format!("❌ {error}").red().bold());
+        std::process::exit(error.exit_code());
+    }
+}
+
+fn run() -> TodoResult<()> {
     let cli = Cli::parse();
+    logging::init(logging::level_filter(cli.quiet, cli.verbose));
+
+    let config_path = Config::default_path();
+    let config = Config::load(&config_path).unwrap_or_else(|error| {
+        log::warn!(
+            "⚠️ Ignoring invalid config {}: {error}",
+            config_path.display()
+        );
+        Config::default()
+    });
+    match config.color {
+        ColorMode::Always => colored::control::set_override(true),
+        ColorMode::Never => colored::control::set_override(false),
+        ColorMode::Auto => {}
+    }
+    config.clone().install();
+
+    // Resolved only by the commands that read or write todos, so the others
+    // work without touching the data directory.
+    let backend = cli.backend.unwrap_or(config.backend);
+    let file = cli.file;
+    let data_file = || {
+        file.clone()
+            .or_else(|| config.data_file.clone())
+            .map_or_else(|| data_path(backend), Ok)
+    };
+    let open = || -> TodoResult<Box<dyn TodoStore>> {
+        let data_file = data_file()?;
+        log::debug!("Using the {backend:?} backend at {}", data_file.display());
+        open_store(backend, &data_file)
+    };
 
     if cli.menu {
-        display_menu(file_path);
-        return;
+        display_menu(open()?.as_mut());
+        return Ok(());
     }
 
     match cli.command {
@@ -47,50 +84,58 @@ fn main() {
             priority,
             status,
             due_date,
+            scheduled,
+            wait_until,
             recurrence,
             tags,
             parent_id,
             subtasks,
         }) => {
-            let due_date = due_date.map(|d| {
-                let nd = NaiveDate::parse_from_str(&d, "%Y-%m-%d").unwrap_or_else(|_| {
-                    panic!(
-                        "{}",
-                        format!("❌ Invalid date format: {d} (expected YYY-MM-DD)").red()
-                    )
-                });
-                let ndt = nd
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap_or_else(|| panic!("{}", "❌  Invalid time components".red()));
-                DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc)
-            });
-
-            let parent_id =
-                parent_id.map(|pid| Uuid::parse_str(&pid).expect("❌ Invalid parent UUID"));
-
-            let subtasks = subtasks.map(|subs| {
-                subs.into_iter()
-                    .map(|s| Uuid::parse_str(&s).expect("❌ Invalid subtask UUID"))
-                    .collect()
-            });
+            let mut store = open()?;
+            let parse =
+                |date: Option<String>| date.map(|d| parse_due(&d, &config.date_format)).transpose();
+            let due_date = parse(due_date)?;
+
+            let ids = store.id_index()?;
+            let parent_id = parent_id.map(|pid| ids.resolve(&pid)).transpose()?;
+            let subtasks = subtasks.map(|subs| ids.resolve_all(&subs)).transpose()?;
 
             add_todo_cli(
-                file_path,
+                store.as_mut(),
                 AddTodoInput {
                     title,
                     description,
-                    priority,
-                    status,
+                    priority: priority.unwrap_or(config.default_priority),
+                    status: status
+                        .map(Status::validate)
+                        .transpose()?
+                        .unwrap_or_else(|| config.default_status.clone()),
                     due_date,
+                    scheduled: parse(scheduled)?,
+                    wait_until: parse(wait_until)?,
                     tags,
                     recurrence,
                     parent_id,
                     subtasks,
                 },
-            );
+            )?;
         }
-        Some(Commands::List { sort_by }) => {
-            list_todos_cli(file_path, &sort_by);
+        Some(Commands::List {
+            sort_by,
+            format,
+            columns,
+            all,
+            filter,
+        }) => {
+            let store = open()?;
+            let filter = filter.map(|f| Filter::parse(&f)).transpose()?;
+            list_todos_cli(
+                store.as_ref(),
+                &sort_by.unwrap_or(config.default_sort),
+                all,
+                filter.as_ref(),
+                &output_options(format, columns),
+            )?;
         }
         Some(Commands::Search {
             id,
@@ -98,39 +143,61 @@ fn main() {
             priority,
             status,
             due_date,
+            due_before,
+            due_after,
+            due,
             recurrence,
             tags,
             parent_task_id,
+            created_since,
+            updated_since,
+            started_since,
+            completed_since,
+            series,
+            filter,
+            text,
+            format,
+            columns,
         }) => {
-            let due_date = due_date.map(|d| {
-                let nd = NaiveDate::parse_from_str(&d, "%Y-%m-%d").unwrap_or_else(|_| {
-                    panic!(
-                        "{}",
-                        format!("❌ Invalid date format: {d} (expected YYY-MM-DD)").red()
-                    )
-                });
-                let ndt = nd
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap_or_else(|| panic!("{}", "❌  Invalid time components".red()));
-                DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc)
-            });
+            let store = open()?;
+            let parse = |date: Option<String>| {
+                date.map(|d| parse_date(&d, &config.date_format))
+                    .transpose()
+            };
+            let to_due =
+                |date: Option<String>| date.map(|d| parse_due(&d, &config.date_format)).transpose();
 
-            let parent_id =
-                parent_task_id.map(|pid| Uuid::parse_str(&pid).expect("❌ Invalid parent UUID"));
+            let parent_id = parent_task_id
+                .map(|pid| store.id_index()?.resolve(&pid))
+                .transpose()?;
 
             search_todo_cli(
-                file_path,
+                store.as_ref(),
                 input::SearchTodoInput {
                     id,
                     title,
                     priority,
-                    status,
-                    due_date,
+                    status: status.map(Status::validate).transpose()?,
+                    due_date: to_due(due_date)?,
+                    due_before: to_due(due_before)?,
+                    due_after: to_due(due_after)?,
+                    due,
                     recurrence,
                     tags,
                     parent_id,
+                    created_since: parse(created_since)?,
+                    updated_since: parse(updated_since)?,
+                    started_since: parse(started_since)?,
+                    completed_since: parse(completed_since)?,
+                    series,
+                    filter: filter.map(|f| Filter::parse(&f)).transpose()?,
+                    text: text.clone(),
                 },
-            );
+                &OutputOptions {
+                    highlight: text,
+                    ..output_options(format, columns)
+                },
+            )?;
         }
         Some(Commands::Update {
             id,
@@ -139,86 +206,55 @@ fn main() {
             priority,
             status,
             due_date,
+            scheduled,
+            wait_until,
             recurrence,
             tags,
             parent_task_id,
             subtasks,
         }) => {
-            let id = match Uuid::parse_str(&id) {
-                Ok(u) => u,
-                Err(_) => {
-                    eprintln!("{}", "❌ Invalid UUID".red());
-                    std::process::exit(1);
-                }
-            };
+            let mut store = open()?;
+            let ids = store.id_index()?;
+            let id = ids.resolve(&id)?;
+            let parse =
+                |date: Option<String>| date.map(|d| parse_due(&d, &config.date_format)).transpose();
 
-            let due_date = due_date.map(|d| {
-                let nd = NaiveDate::parse_from_str(&d, "%Y-%m-%d").unwrap_or_else(|_| {
-                    panic!(
-                        "{}",
-                        format!("❌ Invalid date format: {d} (expected YYY-MM-DD)").red()
-                    )
-                });
-                let ndt = nd
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap_or_else(|| panic!("{}", "❌  Invalid time components".red()));
-                DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc)
-            });
-
-            let parent_id =
-                parent_task_id.map(|pid| Uuid::parse_str(&pid).expect("❌ Invalid parent UUID"));
-
-            let parsed_subtasks = subtasks.map(|subs| {
-                subs.into_iter()
-                    .filter_map(|s| {
-                        Uuid::parse_str(&s)
-                            .inspect_err(|_| {
-                                eprintln!(
-                                    "{}",
-                                    format!("Invalid UUID format in subtasks: {s}")
-                                        .yellow()
-                                        .bold()
-                                );
-                            })
-                            .ok()
-                    })
-                    .collect::<Vec<Uuid>>()
-            });
-
-            if update_todo_cli(
-                file_path,
+            let parent_id = parent_task_id.map(|pid| ids.resolve(&pid)).transpose()?;
+
+            let subtasks = subtasks.map(|subs| ids.resolve_all(&subs)).transpose()?;
+
+            let next = update_todo_cli(
+                store.as_mut(),
                 UpdateTodoInput {
                     id,
                     new_title: title,
                     new_description: description,
                     new_priority: priority,
-                    new_status: status,
-                    new_due_date: due_date,
+                    new_status: status.map(Status::validate).transpose()?,
+                    new_due_date: parse(due_date)?,
+                    new_scheduled: parse(scheduled)?,
+                    new_wait_until: parse(wait_until)?,
                     new_recurrence: recurrence,
                     new_tags: tags,
                     new_parent_id: parent_id,
-                    new_subtasks: parsed_subtasks,
+                    new_subtasks: subtasks,
                 },
-            ) {
-                println!("{}", "✅ Task updated successfully!".green().bold());
-            } else {
-                println!(
-                    "{}",
-                    format!("⚠️ No task found with id {id}").yellow().bold()
-                );
+            )?;
+            println!("{}", "✅ Task updated successfully!".green().bold());
+            if let Some(next) = next {
+                print_next_occurrence(&next);
             }
         }
         Some(Commands::Delete { id }) => {
-            let id = Uuid::parse_str(&id).expect("❌ Invalid UUID");
-            if delete_todo_cli(file_path, id) {
-                println!("{}", "🗑️ Task deleted".red().bold());
-            } else {
-                println!(
-                    "{}",
-                    format!("⚠️ No task found with id {id}").yellow().bold()
-                );
-            }
+            let mut store = open()?;
+            let id = store.id_index()?.resolve(&id)?;
+            delete_todo_cli(store.as_mut(), id)?;
+            println!("{}", "🗑️ Task deleted".red().bold());
         }
+        Some(Commands::Start { ids }) => change_status(open()?.as_mut(), &ids, Status::InProgress)?,
+        Some(Commands::Done { ids }) => change_status(open()?.as_mut(), &ids, Status::Done)?,
+        Some(Commands::Reopen { ids }) => change_status(open()?.as_mut(), &ids, Status::Pending)?,
+        Some(Commands::Cancel { ids }) => change_status(open()?.as_mut(), &ids, Status::Cancelled)?,
         Some(Commands::Completions { shell }) => {
             let mut cmd = Cli::command();
             match shell {
@@ -229,12 +265,84 @@ fn main() {
             }
         }
         Some(Commands::Notify) => {
-            notify::send_due_notifications(file_path);
+            if config.notifications.enabled {
+                notify::send_due_notifications(open()?.as_ref(), &config.notifications)?;
+                println!(
+                    "{}",
+                    "🔔 Notifications dispatched for due tasks".green().bold()
+                );
+            } else {
+                println!(
+                    "{}",
+                    "🔕 Notifications are disabled in the config"
+                        .yellow()
+                        .bold()
+                );
+            }
+        }
+        Some(Commands::Migrate { from, to }) => {
+            let from = from.map_or_else(|| data_path(Backend::Json), Ok)?;
+            let to = to.map_or_else(|| data_path(Backend::Sqlite), Ok)?;
+            let source = JsonFileStore::new(&from);
+            let count = SqliteStore::open(&to)?.import_from(&source)?;
             println!(
                 "{}",
-                "🔔 Notifications dispatched for due tasks".green().bold()
+                format!(
+                    "✅ Migrated {count} todo(s) from {} to {}",
+                    from.display(),
+                    to.display()
+                )
+                .green()
+                .bold()
             );
         }
+        Some(Commands::Config { action }) => match action {
+            ConfigAction::Show => {
+                println!("{}", format!("# {}", config_path.display()).dimmed());
+                print!("{}", config.to_toml());
+            }
+            ConfigAction::Get { key } => match config.get(&key) {
+                Some(toml::Value::String(value)) => println!("{value}"),
+                Some(value) => println!("{value}"),
+                None => {
+                    return Err(TodoError::Config(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unknown config key: {key}"),
+                    )));
+                }
+            },
+            ConfigAction::Set { key, value } => {
+                config::set_value(&config_path, &key, &value).map_err(TodoError::Config)?;
+                println!("{}", format!("✅ Set {key} = {value}").green().bold());
+            }
+        },
+        Some(Commands::Repair) => {
+            if backend != Backend::Json {
+                return Err(TodoError::Unsupported(
+                    "repair only applies to the JSON backend".into(),
+                ));
+            }
+            let data_file = data_file()?;
+            match JsonFileStore::new(&data_file).repair()? {
+                Some(report) => println!(
+                    "{}",
+                    format!(
+                        "🩹 Salvaged {} todo(s) into {}; the damaged file was kept as {}",
+                        report.salvaged,
+                        data_file.display(),
+                        report.original.display()
+                    )
+                    .green()
+                    .bold()
+                ),
+                None => println!(
+                    "{}",
+                    format!("✅ {} is healthy, nothing to repair", data_file.display())
+                        .green()
+                        .bold()
+                ),
+            }
+        }
         None => {
             println!(
                 "{}",
@@ -244,4 +352,71 @@ fn main() {
             );
         }
     }
+    Ok(())
+}
+
+fn change_status(store: &mut dyn TodoStore, inputs: &[String], status: Status) -> TodoResult<()> {
+    let ids = store.id_index()?;
+    let changes = set_status_cli(store, &ids.resolve_all(inputs)?, status)?;
+    for change in changes {
+        let short_id = ids.short_id(change.id);
+        if change.from == change.to {
+            println!(
+                "{}",
+                format!("➖ {short_id} {} is already {}", change.title, change.to).dimmed()
+            );
+        } else {
+            println!(
+                "{}",
+                format!(
+                    "✅ {short_id} {}: {} → {}",
+                    change.title, change.from, change.to
+                )
+                .green()
+                .bold()
+            );
+        }
+        if let Some(next) = change.next {
+            print_next_occurrence(&next);
+        }
+    }
+    Ok(())
+}
+
+fn print_next_occurrence(next: &Todo) {
+    let due = next.due_date.map(|due| due.to_string()).unwrap_or_default();
+    println!(
+        "{}",
+        format!("🔁 Next occurrence of {} is due {due}", next.title).cyan()
+    );
+}
+
+/// `--columns` implies the table format unless another one was asked for.
+fn output_options(format: Option<OutputFormat>, columns: Option<Vec<Column>>) -> OutputOptions {
+    let format = format.unwrap_or(if columns.is_some() {
+        OutputFormat::Table
+    } else {
+        OutputFormat::Human
+    });
+    OutputOptions {
+        format,
+        columns: columns.unwrap_or_default(),
+        highlight: None,
+    }
+}
+
+fn open_store(backend: Backend, path: &Path) -> TodoResult<Box<dyn TodoStore>> {
+    Ok(match backend {
+        Backend::Json => Box::new(JsonFileStore::new(path)),
+        Backend::Sqlite => Box::new(SqliteStore::open(path)?),
+    })
+}
+
+/// Default data file for `backend`, creating its directory on first use.
+fn data_path(backend: Backend) -> TodoResult<PathBuf> {
+    let path = default_data_path(backend.default_file_name());
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(path)
 }
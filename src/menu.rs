@@ -1,10 +1,11 @@
 use crate::{
+    storage::TodoStore,
     todo::{add_todo, delete_todo, retrieve_todos_sorted, search_menu, update_todo},
     utils::read_input,
 };
 use colored::*;
 
-pub fn display_menu(file_path: &str) {
+pub fn display_menu(store: &mut dyn TodoStore) {
     println!("{}", "🎉 Welcome to the To-Do list System!".bold().blue());
 
     loop {
@@ -20,11 +21,11 @@ pub fn display_menu(file_path: &str) {
         let choice = read_input::<u32>();
 
         match choice {
-            1 => add_todo(file_path),
-            2 => retrieve_todos_sorted(file_path),
-            3 => search_menu(file_path),
-            4 => update_todo(file_path),
-            5 => delete_todo(file_path),
+            1 => add_todo(store),
+            2 => retrieve_todos_sorted(store),
+            3 => search_menu(store),
+            4 => update_todo(store),
+            5 => delete_todo(store),
             6 => {
                 println!("{}", "👋 Goodbye!".bold().green());
                 break;
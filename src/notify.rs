@@ -1,34 +1,42 @@
-use crate::storage::load_todos_from_file;
-use chrono::Utc;
+use crate::dates::Zone;
+use crate::{config::NotificationConfig, error::TodoResult, storage::TodoStore};
+use chrono::{Duration, Utc};
 use notify_rust::Notification;
 
-pub fn send_due_notifications(file_path: &str) {
-    let todos = load_todos_from_file(file_path);
-    if todos.is_empty() {
-        return;
-    }
+pub fn send_due_notifications(
+    store: &dyn TodoStore,
+    settings: &NotificationConfig,
+) -> TodoResult<()> {
+    let todos = store.load()?;
 
     let now = Utc::now();
+    let zone = Zone::current();
+    let horizon = now + Duration::minutes(settings.lead_time_minutes);
     for (_id, todo) in todos.iter() {
-        if let Some(due) = todo.due_date {
-            if due <= now {
-                let title = format!(
-                    "{}: {}",
-                    if due < now { "Overdue" } else { "Due" },
-                    todo.title
-                );
-                let body = format!(
-                    "{}\nPriority: {}\nStatus: {}\nDue: {}",
-                    todo.description
-                        .as_deref()
-                        .unwrap_or("No description provided"),
-                    todo.priority,
-                    todo.status,
-                    due
-                );
-                let _ = Notification::new().summary(&title).body(&body).show();
-            }
+        if let Some(due) = todo.due_date
+            && due.start(zone) <= horizon
+            && (settings.include_overdue || !due.is_overdue_at(now, zone))
+        {
+            let title = format!(
+                "{}: {}",
+                if due.is_overdue_at(now, zone) {
+                    "Overdue"
+                } else {
+                    "Due"
+                },
+                todo.title
+            );
+            let body = format!(
+                "{}\nPriority: {}\nStatus: {}\nDue: {}",
+                todo.description
+                    .as_deref()
+                    .unwrap_or("No description provided"),
+                todo.priority,
+                todo.status,
+                due
+            );
+            let _ = Notification::new().summary(&title).body(&body).show();
         }
     }
+    Ok(())
 }
-
@@ -0,0 +1,343 @@
+use crate::{
+    config::Config,
+    dates::Zone,
+    fulltext::{self, TextQuery},
+    ids::IdIndex,
+    table::{self, Column},
+    todo::Todo,
+};
+use clap::ValueEnum;
+use colored::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{self, Write};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored multi-line blocks.
+    #[default]
+    Human,
+    /// One aligned row per todo.
+    Table,
+    /// A single JSON array.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+/// A todo as written by the machine-readable formats: every stored field
+/// plus values computed at output time.
+#[derive(Debug, Serialize)]
+pub struct TodoRecord<'a> {
+    #[serde(flatten)]
+    pub todo: &'a Todo,
+    pub number: Option<usize>,
+    pub short_id: String,
+    pub overdue: bool,
+}
+
+impl<'a> TodoRecord<'a> {
+    pub fn new(todo: &'a Todo, ids: &IdIndex) -> Self {
+        Self {
+            todo,
+            number: ids.number(todo.id),
+            short_id: ids.short_id(todo.id),
+            overdue: todo.is_overdue(),
+        }
+    }
+}
+
+/// Column order of the CSV output. Names match the JSON keys.
+pub const CSV_COLUMNS: &[&str] = &[
+    "number",
+    "id",
+    "short_id",
+    "title",
+    "description",
+    "priority",
+    "status",
+    "created_at",
+    "updated_at",
+    "due_date",
+    "scheduled",
+    "wait_until",
+    "overdue",
+    "started_at",
+    "completed_at",
+    "tags",
+    "parent_id",
+    "subtasks",
+    "recurrence",
+    "series_id",
+];
+
+/// How `list` and `search` print their results.
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    /// Columns for the table format; empty means the default set.
+    pub columns: Vec<Column>,
+    /// Words to highlight in the human format.
+    pub highlight: Option<TextQuery>,
+}
+
+/// Writes `todos` to stdout. Tables are fitted to the terminal width.
+pub fn print_todos(todos: &[&Todo], ids: &IdIndex, options: &OutputOptions) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    write_todos(&mut out, todos, ids, options, table::terminal_width())?;
+    out.flush()
+}
+
+pub fn write_todos(
+    out: &mut dyn Write,
+    todos: &[&Todo],
+    ids: &IdIndex,
+    options: &OutputOptions,
+    max_width: Option<usize>,
+) -> io::Result<()> {
+    match options.format {
+        OutputFormat::Human => todos
+            .iter()
+            .try_for_each(|todo| write_human(out, todo, ids, options.highlight.as_ref())),
+        OutputFormat::Table => table::write_table(out, todos, ids, &options.columns, max_width),
+        OutputFormat::Json => {
+            let records: Vec<TodoRecord> = todos
+                .iter()
+                .map(|todo| TodoRecord::new(todo, ids))
+                .collect();
+            serde_json::to_writer_pretty(&mut *out, &records)?;
+            writeln!(out)
+        }
+        OutputFormat::Ndjson => todos.iter().try_for_each(|&todo| {
+            serde_json::to_writer(&mut *out, &TodoRecord::new(todo, ids))?;
+            writeln!(out)
+        }),
+        OutputFormat::Csv => write_csv(out, todos, ids),
+    }
+}
+
+fn write_human(
+    out: &mut dyn Write,
+    todo: &Todo,
+    ids: &IdIndex,
+    highlight: Option<&TextQuery>,
+) -> io::Result<()> {
+    // Matches stand out in reverse video on top of the field's own style.
+    let paint = |text: &str, style: fn(&str) -> ColoredString| match highlight {
+        Some(query) => fulltext::segments(text, query)
+            .into_iter()
+            .map(|(piece, hit)| {
+                if hit {
+                    style(piece).reversed().to_string()
+                } else {
+                    style(piece).to_string()
+                }
+            })
+            .collect(),
+        None => style(text).to_string(),
+    };
+
+    let priority_color = todo.priority.paint(&todo.priority.to_string());
+
+    let status_color = todo.status.paint(&todo.status.to_string());
+
+    let id = match ids.number(todo.id) {
+        Some(number) => format!("{} (#{number})", ids.short_id(todo.id)),
+        None => todo.id.to_string(),
+    };
+    writeln!(out, "{:<10} {}", "ID:".bold(), id.cyan())?;
+    writeln!(
+        out,
+        "{:<10} {}",
+        "Title:".bold(),
+        paint(&todo.title, |text| text.bold())
+    )?;
+    writeln!(out, "{:<10} {}", "Priority:".bold(), priority_color)?;
+    writeln!(out, "{:<10} {}", "Status:".bold(), status_color)?;
+    writeln!(
+        out,
+        "{:<10} {}",
+        "Description:".bold(),
+        paint(todo.description.as_deref().unwrap_or("None"), |text| text
+            .normal())
+    )?;
+    let date_format = &Config::current().date_format;
+    let timestamp_format = format!("{date_format} %H:%M");
+    let zone = Zone::current();
+    let local = |at| zone.local(at).format(&timestamp_format).to_string();
+    writeln!(out, "{:<10} {}", "Created:".bold(), local(todo.created_at))?;
+    if todo.updated_at != todo.created_at {
+        writeln!(out, "{:<10} {}", "Updated:".bold(), local(todo.updated_at))?;
+    }
+    if let Some(started) = todo.started_at {
+        writeln!(out, "{:<10} {}", "Started:".bold(), local(started))?;
+    }
+    if let Some(completed) = todo.completed_at {
+        writeln!(out, "{:<10} {}", "Completed:".bold(), local(completed))?;
+    }
+
+    if let Some(due) = todo.due_date {
+        let due_str = due.format(date_format, zone);
+        if todo.is_overdue() {
+            let overdue_str = format!("{due_str} ⚠️ Overdue!");
+            writeln!(out, "{:<10} {}", "Due Date:".bold(), overdue_str.red())?;
+        } else {
+            writeln!(out, "{:<10} {}", "Due Date:".bold(), due_str.yellow())?;
+        }
+    }
+    if let Some(scheduled) = todo.scheduled {
+        writeln!(
+            out,
+            "{:<10} {}",
+            "Scheduled:".bold(),
+            scheduled.format(date_format, zone)
+        )?;
+    }
+    if let Some(wait_until) = todo.wait_until {
+        writeln!(
+            out,
+            "{:<10} {}",
+            "Wait Until:".bold(),
+            wait_until.format(date_format, zone)
+        )?;
+    }
+
+    if let Some(tags) = &todo.tags {
+        writeln!(
+            out,
+            "{:<10} {}",
+            "Tags:".bold(),
+            paint(&tags.join(", "), |text| text.cyan())
+        )?;
+    }
+
+    if let Some(pid) = todo.parent_id {
+        writeln!(
+            out,
+            "{:<10} {}",
+            "Parent ID:".bold(),
+            pid.to_string().blue()
+        )?;
+    }
+
+    if let Some(subs) = &todo.subtasks {
+        let subs_str: Vec<String> = subs.iter().map(|id| id.to_string()).collect();
+        writeln!(
+            out,
+            "{:<10} {}",
+            "Subtasks:".bold(),
+            subs_str.join(", ").purple()
+        )?;
+    }
+
+    if let Some(rec) = &todo.recurrence {
+        writeln!(
+            out,
+            "{:<10} {}",
+            "Recurrence:".bold(),
+            rec.to_string().yellow()
+        )?;
+    }
+    if let Some(series) = todo.series_id {
+        writeln!(
+            out,
+            "{:<10} {}",
+            "Series:".bold(),
+            ids.short_id(series).yellow()
+        )?;
+    }
+    writeln!(out)
+}
+
+fn write_csv(out: &mut dyn Write, todos: &[&Todo], ids: &IdIndex) -> io::Result<()> {
+    writeln!(out, "{}", CSV_COLUMNS.join(","))?;
+    for &todo in todos {
+        let record = serde_json::to_value(TodoRecord::new(todo, ids))?;
+        let row: Vec<String> = CSV_COLUMNS
+            .iter()
+            .map(|column| csv_field(&csv_text(&record[column])))
+            .collect();
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+/// Flattens a JSON value into a single CSV cell; lists are joined with `;`.
+fn csv_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(csv_text).collect::<Vec<_>>().join(";"),
+        other => other.to_string(),
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{due::Due, input::AddTodoInput, priority::Priority};
+    use chrono::{Duration, Utc};
+
+    fn sample_todo() -> Todo {
+        Todo::new(AddTodoInput {
+            description: Some("Say \"hi\"".into()),
+            priority: Priority::High,
+            due_date: Some(Due::At(Utc::now() - Duration::days(1))),
+            tags: Some(vec!["home".into(), "errand".into()]),
+            ..AddTodoInput::new("Buy milk, eggs")
+        })
+    }
+
+    fn render(todos: &[&Todo], format: OutputFormat) -> String {
+        let mut out = Vec::new();
+        let options = OutputOptions {
+            format,
+            ..Default::default()
+        };
+        let all = todos.iter().map(|&todo| (todo.id, todo.clone())).collect();
+        write_todos(&mut out, todos, &IdIndex::new(&all), &options, None).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn json_formats_include_computed_fields() {
+        let todo = sample_todo();
+
+        let json: Value = serde_json::from_str(&render(&[&todo], OutputFormat::Json)).unwrap();
+        assert_eq!(json[0]["title"], "Buy milk, eggs");
+        assert_eq!(json[0]["overdue"], true);
+
+        let ndjson = render(&[&todo, &todo], OutputFormat::Ndjson);
+        assert_eq!(ndjson.lines().count(), 2);
+        let first: Value = serde_json::from_str(ndjson.lines().next().unwrap()).unwrap();
+        assert_eq!(first["id"], todo.id.to_string());
+    }
+
+    #[test]
+    fn csv_quotes_fields_and_joins_lists() {
+        let todo = sample_todo();
+        let csv = render(&[&todo], OutputFormat::Csv);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), CSV_COLUMNS.join(","));
+        let row = lines.next().unwrap();
+        assert!(row.contains(r#","Buy milk, eggs","Say ""hi""",High,Pending,"#));
+        assert!(row.contains(",true,,,home;errand,"));
+    }
+
+    #[test]
+    fn empty_results_stay_parseable() {
+        assert_eq!(render(&[], OutputFormat::Json).trim(), "[]");
+        assert_eq!(render(&[], OutputFormat::Ndjson), "");
+        assert_eq!(render(&[], OutputFormat::Csv).trim(), CSV_COLUMNS.join(","));
+    }
+}
@@ -1,4 +1,6 @@
+use crate::config::Config;
 use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Result};
 
@@ -9,6 +11,21 @@ pub enum Priority {
     Low,
 }
 
+impl Priority {
+    /// Colors `text` the way this priority is shown everywhere, using the
+    /// theme's color if the config sets one.
+    pub fn paint(self, text: &str) -> ColoredString {
+        if let Some(color) = Config::current().theme.priority_color(self) {
+            return text.color(color);
+        }
+        match self {
+            Priority::High => text.red().bold(),
+            Priority::Medium => text.yellow(),
+            Priority::Low => text.green(),
+        }
+    }
+}
+
 impl Display for Priority {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         let label = match self {
@@ -1,33 +1,878 @@
-use serde::{Deserialize, Serialize};
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+use serde_json::Value;
 use std::{
     fmt::{Display, Formatter, Result as FmtResult},
     str::FromStr,
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
-pub enum Recurrence {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Frequency {
     Daily,
     Weekly,
-    Custom(String),
+    Monthly,
+    Yearly,
 }
 
+/// Which day of the month a monthly or yearly rule falls on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MonthDay {
+    /// A fixed day from 1 to 31; shorter months use their last day.
+    Day(u32),
+    /// The last day of the month.
+    Last,
+    /// The nth weekday of the month, e.g. the 2nd Tuesday; `-1` is the
+    /// last one.
+    Nth(i32, Weekday),
+}
+
+/// When a rule stops producing occurrences.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum End {
+    /// No occurrences after this date.
+    Until(NaiveDate),
+    /// This many occurrences left, counting the current one. Each new
+    /// occurrence carries one fewer.
+    Count(u32),
+}
+
+/// What the next occurrence of a recurring todo is scheduled from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Anchor {
+    /// The calendar: the next date the rule falls on after the due date.
+    #[default]
+    Due,
+    /// The day the previous occurrence was done, e.g. "water the plants
+    /// 3 days after they were last watered".
+    Completion,
+}
+
+/// The largest interval a rule accepts, so the date arithmetic on it can
+/// never overflow.
+pub const MAX_INTERVAL: u32 = 1000;
+
+/// How a todo repeats, e.g. "every 2 weeks on mon,thu until 2026-12-31".
+///
+/// Parsed from that kind of text or from an RFC 5545 `RRULE`, and stored as
+/// the latter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Repeat every `interval` days, weeks, months or years.
+    pub interval: u32,
+    /// Days a weekly rule falls on, Monday first. Empty means the weekday
+    /// of the due date.
+    pub weekdays: Vec<Weekday>,
+    /// Day a monthly or yearly rule falls on. `None` means the day of the
+    /// due date.
+    pub month_day: Option<MonthDay>,
+    pub end: Option<End>,
+    pub anchor: Anchor,
+}
+
+impl Recurrence {
+    pub fn every(interval: u32, frequency: Frequency) -> Self {
+        Self {
+            frequency,
+            interval,
+            weekdays: Vec::new(),
+            month_day: None,
+            end: None,
+            anchor: Anchor::Due,
+        }
+    }
+
+    /// When the occurrence after one due on `due` and done on `done` is
+    /// due, along with the rule it carries.
+    ///
+    /// Calendar rules skip occurrences that had already passed when the
+    /// todo was done, so finishing a daily todo a week late schedules one
+    /// for that day rather than seven overdue ones. Skipped occurrences
+    /// still count towards an `N times` limit.
+    pub fn next_occurrence(&self, due: NaiveDate, done: NaiveDate) -> Option<(NaiveDate, Self)> {
+        if self.anchor == Anchor::Completion {
+            return Some((self.next_after(done)?, self.advanced()));
+        }
+        let (mut date, mut rule) = (due, self.clone());
+        loop {
+            date = rule.next_after(date)?;
+            rule = rule.advanced();
+            if date >= done {
+                return Some((date, rule));
+            }
+        }
+    }
+
+    /// The first date after `date` that the rule falls on, or `None` once
+    /// it has ended.
+    pub fn next_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        if let Some(End::Count(..=1)) = self.end {
+            return None;
+        }
+        let next = match self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(self.interval.into()))?,
+            Frequency::Weekly => self.next_weekly(date)?,
+            Frequency::Monthly => self.next_in_months(date, self.interval)?,
+            Frequency::Yearly => self.next_in_months(date, self.interval.checked_mul(12)?)?,
+        };
+        match self.end {
+            Some(End::Until(until)) if next > until => None,
+            _ => Some(next),
+        }
+    }
+
+    /// The rule the next occurrence carries: one fewer left if the rule
+    /// ends after a number of occurrences.
+    fn advanced(&self) -> Self {
+        let mut next = self.clone();
+        if let Some(End::Count(count)) = &mut next.end {
+            *count = count.saturating_sub(1).max(1);
+        }
+        next
+    }
+
+    /// A later weekday in the same week, otherwise the first weekday of the
+    /// week `interval` weeks on.
+    fn next_weekly(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let today = date.weekday().num_days_from_monday();
+        if let Some(day) = self
+            .weekdays
+            .iter()
+            .map(Weekday::num_days_from_monday)
+            .find(|&day| day > today)
+        {
+            return date.checked_add_days(Days::new((day - today).into()));
+        }
+        let first = self
+            .weekdays
+            .first()
+            .map_or(today, Weekday::num_days_from_monday);
+        let days = self.interval.checked_mul(7)?.checked_add(first)?;
+        date.checked_sub_days(Days::new(today.into()))?
+            .checked_add_days(Days::new(days.into()))
+    }
+
+    /// The first matching day after `date`, looking at every `step`th
+    /// month. Months without a matching day (no 5th Friday) are skipped.
+    fn next_in_months(&self, date: NaiveDate, step: u32) -> Option<NaiveDate> {
+        let month = date.with_day(1)?;
+        (0..=60)
+            .filter_map(|k| month.checked_add_months(Months::new(step.checked_mul(k)?)))
+            .filter_map(|first| self.day_in_month(first, date.day()))
+            .find(|&candidate| candidate > date)
+    }
+
+    /// The day this rule picks in the month starting at `first`; without a
+    /// `month_day`, `default_day` capped at the month's length.
+    fn day_in_month(&self, first: NaiveDate, default_day: u32) -> Option<NaiveDate> {
+        let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+        match self.month_day {
+            None => first.with_day(default_day.min(last.day())),
+            Some(MonthDay::Day(day)) => first.with_day(day.min(last.day())),
+            Some(MonthDay::Last) => Some(last),
+            Some(MonthDay::Nth(-1, weekday)) => {
+                let back = (7 + last.weekday().num_days_from_monday()
+                    - weekday.num_days_from_monday())
+                    % 7;
+                last.checked_sub_days(Days::new(back.into()))
+            }
+            Some(MonthDay::Nth(n, weekday)) => NaiveDate::from_weekday_of_month_opt(
+                first.year(),
+                first.month(),
+                weekday,
+                u8::try_from(n).ok()?,
+            ),
+        }
+    }
+
+    /// The rule as an RFC 5545 recurrence rule, without the `RRULE:` prefix.
+    pub fn to_rrule(&self) -> String {
+        let mut parts = vec![format!(
+            "FREQ={}",
+            match self.frequency {
+                Frequency::Daily => "DAILY",
+                Frequency::Weekly => "WEEKLY",
+                Frequency::Monthly => "MONTHLY",
+                Frequency::Yearly => "YEARLY",
+            }
+        )];
+        if self.interval != 1 {
+            parts.push(format!("INTERVAL={}", self.interval));
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<&str> = self.weekdays.iter().map(|&day| rrule_day(day)).collect();
+            parts.push(format!("BYDAY={}", days.join(",")));
+        }
+        match self.month_day {
+            Some(MonthDay::Day(day)) => parts.push(format!("BYMONTHDAY={day}")),
+            Some(MonthDay::Last) => parts.push("BYMONTHDAY=-1".into()),
+            Some(MonthDay::Nth(n, day)) => parts.push(format!("BYDAY={n}{}", rrule_day(day))),
+            None => {}
+        }
+        match self.end {
+            Some(End::Until(date)) => parts.push(format!("UNTIL={}", date.format("%Y%m%d"))),
+            Some(End::Count(count)) => parts.push(format!("COUNT={count}")),
+            None => {}
+        }
+        if self.anchor == Anchor::Completion {
+            parts.push("X-ANCHOR=COMPLETION".into());
+        }
+        parts.join(";")
+    }
+
+    /// Rejects combinations the model allows but that mean nothing, such
+    /// as weekdays on a monthly rule.
+    fn validate(self) -> Result<Self, String> {
+        if self.interval == 0 {
+            return Err("the interval must be at least 1".into());
+        }
+        if self.interval > MAX_INTERVAL {
+            return Err(format!("the interval can be at most {MAX_INTERVAL}"));
+        }
+        if !self.weekdays.is_empty() && self.frequency != Frequency::Weekly {
+            return Err("weekdays can only be given for weekly rules".into());
+        }
+        if self.month_day.is_some()
+            && !matches!(self.frequency, Frequency::Monthly | Frequency::Yearly)
+        {
+            return Err("a day of the month needs a monthly or yearly rule".into());
+        }
+        match self.month_day {
+            Some(MonthDay::Day(day)) if !(1..=31).contains(&day) => {
+                return Err(format!("{day} is not a day of the month"));
+            }
+            Some(MonthDay::Nth(n, _)) if !(1..=5).contains(&n) && n != -1 => {
+                return Err(format!("{n} is not a week of the month"));
+            }
+            _ => {}
+        }
+        if self.end == Some(End::Count(0)) {
+            return Err("the occurrence count must be at least 1".into());
+        }
+        Ok(self)
+    }
+
+    /// Reads a rule stored before rules were structured: `"Daily"`,
+    /// `"Weekly"` or `{"Custom": text}`. Custom text that is not a valid
+    /// rule is returned as the error, so callers can keep it elsewhere.
+    pub fn from_legacy(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::String(name) if name == "Daily" => Ok(Self::every(1, Frequency::Daily)),
+            Value::String(name) if name == "Weekly" => Ok(Self::every(1, Frequency::Weekly)),
+            Value::Object(map) => match map.get("Custom").and_then(Value::as_str) {
+                Some(text) => text.parse().map_err(|_| text.to_string()),
+                None => Err(value.to_string()),
+            },
+            other => Err(other.to_string()),
+        }
+    }
+}
+
+/// Appends recurrence text that could not be converted to a rule to a
+/// todo's description, so upgrading never silently drops it.
+pub fn keep_unparsed(description: Option<&str>, text: &str) -> String {
+    match description {
+        Some(description) if !description.is_empty() => {
+            format!("{description}\n\nRecurrence: {text}")
+        }
+        _ => format!("Recurrence: {text}"),
+    }
+}
+
+/// Human-readable form that parses back to the same rule.
 impl Display for Recurrence {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        match self {
-            Recurrence::Daily => f.write_str("Daily"),
-            Recurrence::Weekly => f.write_str("Weekly"),
-            Recurrence::Custom(text) => write!(f, "Custom({text})"),
+        let unit = match self.frequency {
+            Frequency::Daily => "day",
+            Frequency::Weekly => "week",
+            Frequency::Monthly => "month",
+            Frequency::Yearly => "year",
+        };
+        match self.interval {
+            1 => write!(f, "every {unit}")?,
+            n => write!(f, "every {n} {unit}s")?,
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<String> = self
+                .weekdays
+                .iter()
+                .map(|day| day.to_string().to_lowercase())
+                .collect();
+            write!(f, " on {}", days.join(","))?;
+        }
+        match self.month_day {
+            Some(MonthDay::Day(day)) => write!(f, " on the {}", ordinal(day as i32))?,
+            Some(MonthDay::Last) => write!(f, " on the last day")?,
+            Some(MonthDay::Nth(n, day)) => write!(
+                f,
+                " on the {} {}",
+                ordinal(n),
+                day.to_string().to_lowercase()
+            )?,
+            None => {}
+        }
+        if self.anchor == Anchor::Completion {
+            write!(f, " after completion")?;
+        }
+        match self.end {
+            Some(End::Until(date)) => write!(f, " until {}", date.format("%Y-%m-%d")),
+            Some(End::Count(1)) => write!(f, " 1 time"),
+            Some(End::Count(count)) => write!(f, " {count} times"),
+            None => Ok(()),
         }
     }
 }
 
+/// Accepts text such as `daily`, `every 2 weeks on mon,thu`,
+/// `every month on the last fri until 2026-12-31`, `every weekday 10 times`,
+/// `every 3 days after completion` or an RRULE such as
+/// `FREQ=MONTHLY;BYMONTHDAY=-1`. The non-standard `X-ANCHOR=COMPLETION`
+/// part marks completion-based rules.
 impl FromStr for Recurrence {
     type Err = String;
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "daily" => Ok(Recurrence::Daily),
-            "weekly" => Ok(Recurrence::Weekly),
-            other => Ok(Recurrence::Custom(other.to_string())),
+        let text = s.trim().to_lowercase();
+        let rule = if text.starts_with("rrule:") || text.starts_with("freq=") {
+            parse_rrule(&text)
+        } else {
+            parse_text(&text)
+        };
+        rule.and_then(Recurrence::validate)
+            .map_err(|error| format!("invalid recurrence {:?}: {error}", s.trim()))
+    }
+}
+
+impl Serialize for Recurrence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_rrule())
+    }
+}
+
+impl<'de> Deserialize<'de> for Recurrence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+fn parse_text(text: &str) -> Result<Recurrence, String> {
+    let cleaned = text.replace(',', " ");
+    let mut words = cleaned
+        .split_whitespace()
+        .filter(|word| !matches!(*word, "and" | "the" | "of"))
+        .peekable();
+
+    let mut rule = match words.next() {
+        Some("daily") => Recurrence::every(1, Frequency::Daily),
+        Some("weekly") => Recurrence::every(1, Frequency::Weekly),
+        Some("monthly") => Recurrence::every(1, Frequency::Monthly),
+        Some("yearly" | "annually") => Recurrence::every(1, Frequency::Yearly),
+        Some("every") => {
+            let interval = match words.peek() {
+                Some(&"other") => {
+                    words.next();
+                    2
+                }
+                Some(word) => match word.parse::<u32>() {
+                    Ok(n) => {
+                        words.next();
+                        n
+                    }
+                    Err(_) => 1,
+                },
+                None => return Err("expected a unit after \"every\"".into()),
+            };
+            match words.next() {
+                Some("day" | "days") => Recurrence::every(interval, Frequency::Daily),
+                Some("week" | "weeks") => Recurrence::every(interval, Frequency::Weekly),
+                Some("month" | "months") => Recurrence::every(interval, Frequency::Monthly),
+                Some("year" | "years") => Recurrence::every(interval, Frequency::Yearly),
+                Some("weekday" | "weekdays") => Recurrence {
+                    weekdays: vec![
+                        Weekday::Mon,
+                        Weekday::Tue,
+                        Weekday::Wed,
+                        Weekday::Thu,
+                        Weekday::Fri,
+                    ],
+                    ..Recurrence::every(interval, Frequency::Weekly)
+                },
+                Some(word) => match word.parse::<Weekday>() {
+                    Ok(day) => {
+                        let mut weekdays = vec![day];
+                        weekdays.extend(take_weekdays(&mut words));
+                        Recurrence {
+                            weekdays,
+                            ..Recurrence::every(interval, Frequency::Weekly)
+                        }
+                    }
+                    Err(_) => return Err(format!("unknown unit {word:?}")),
+                },
+                None => return Err("expected day, week, month or year".into()),
+            }
         }
+        Some(word) => return Err(format!("expected \"every\", got {word:?}")),
+        None => return Err("the rule is empty".into()),
+    };
+
+    if words.next_if_eq(&"on").is_some() {
+        match rule.frequency {
+            Frequency::Weekly => {
+                rule.weekdays.extend(take_weekdays(&mut words));
+                if rule.weekdays.is_empty() {
+                    return Err("expected weekdays after \"on\"".into());
+                }
+            }
+            _ => rule.month_day = Some(parse_month_day(&mut words)?),
+        }
+    }
+    rule.weekdays.sort_by_key(Weekday::num_days_from_monday);
+    rule.weekdays.dedup();
+
+    if words
+        .next_if(|word| matches!(*word, "after" | "from"))
+        .is_some()
+    {
+        match words.next() {
+            Some("completion" | "done" | "completed") => rule.anchor = Anchor::Completion,
+            _ => return Err("expected \"completion\" after \"after\"".into()),
+        }
+    }
+
+    rule.end = match words.next() {
+        Some("until") => {
+            let date = words.next().ok_or("expected a date after \"until\"")?;
+            Some(End::Until(
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("{date:?} is not a date (expected YYYY-MM-DD)"))?,
+            ))
+        }
+        Some(word) => {
+            let count = if word == "for" {
+                words.next().unwrap_or_default()
+            } else {
+                word
+            };
+            let count = count
+                .parse::<u32>()
+                .map_err(|_| format!("unexpected {word:?}"))?;
+            match words.next() {
+                Some("time" | "times" | "occurrence" | "occurrences") => Some(End::Count(count)),
+                _ => return Err(format!("expected \"times\" after {count}")),
+            }
+        }
+        None => None,
+    };
+
+    match words.next() {
+        Some(word) => Err(format!("unexpected {word:?}")),
+        None => Ok(rule),
+    }
+}
+
+fn take_weekdays<'a>(
+    words: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+) -> Vec<Weekday> {
+    let mut days = Vec::new();
+    while let Some(day) = words.peek().and_then(|word| word.parse::<Weekday>().ok()) {
+        words.next();
+        days.push(day);
+    }
+    days
+}
+
+/// Reads `15th`, `last day`, `2nd tue` or `last friday`.
+fn parse_month_day<'a>(
+    words: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+) -> Result<MonthDay, String> {
+    let word = words.next().ok_or("expected a day after \"on\"")?;
+    let n = parse_ordinal(word).ok_or_else(|| format!("expected a day, got {word:?}"))?;
+    match words.peek().copied() {
+        Some("day") if n == -1 => {
+            words.next();
+            Ok(MonthDay::Last)
+        }
+        Some(next) => match next.parse::<Weekday>() {
+            Ok(day) => {
+                words.next();
+                Ok(MonthDay::Nth(n, day))
+            }
+            Err(_) if n > 0 => Ok(MonthDay::Day(n as u32)),
+            Err(_) => Err("expected \"day\" or a weekday after \"last\"".into()),
+        },
+        None if n > 0 => Ok(MonthDay::Day(n as u32)),
+        None => Err("expected \"day\" or a weekday after \"last\"".into()),
+    }
+}
+
+/// `1`, `1st`, `first` … and `last` as -1.
+fn parse_ordinal(word: &str) -> Option<i32> {
+    let named = ["first", "second", "third", "fourth", "fifth"];
+    if word == "last" {
+        return Some(-1);
+    }
+    if let Some(i) = named.iter().position(|name| *name == word) {
+        return Some(i as i32 + 1);
+    }
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+    match suffix {
+        "" | "st" | "nd" | "rd" | "th" => digits.parse().ok(),
+        _ => None,
+    }
+}
+
+fn ordinal(n: i32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        _ if n == -1 => return "last".into(),
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+fn parse_rrule(text: &str) -> Result<Recurrence, String> {
+    let text = text.strip_prefix("rrule:").unwrap_or(text);
+    let mut frequency = None;
+    let mut rule = Recurrence::every(1, Frequency::Daily);
+    let mut by_day = Vec::new();
+    let set_end = |rule: &mut Recurrence, end| match rule.end.replace(end) {
+        Some(_) => Err("COUNT and UNTIL cannot both be given".to_string()),
+        None => Ok(()),
+    };
+
+    for part in text.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("expected KEY=VALUE, got {part:?}"))?;
+        match key {
+            "freq" => {
+                frequency = Some(match value {
+                    "daily" => Frequency::Daily,
+                    "weekly" => Frequency::Weekly,
+                    "monthly" => Frequency::Monthly,
+                    "yearly" => Frequency::Yearly,
+                    _ => return Err(format!("unsupported FREQ {value:?}")),
+                })
+            }
+            "interval" => {
+                rule.interval = value
+                    .parse()
+                    .map_err(|_| format!("invalid INTERVAL {value:?}"))?
+            }
+            "byday" => {
+                for day in value.split(',') {
+                    let code = day.get(day.len().saturating_sub(2)..).unwrap_or_default();
+                    let weekday =
+                        parse_rrule_day(code).ok_or_else(|| format!("invalid BYDAY {day:?}"))?;
+                    let n = &day[..day.len() - 2];
+                    let n = match n {
+                        "" => None,
+                        _ => Some(
+                            n.trim_start_matches('+')
+                                .parse::<i32>()
+                                .map_err(|_| format!("invalid BYDAY {day:?}"))?,
+                        ),
+                    };
+                    by_day.push((n, weekday));
+                }
+            }
+            "bymonthday" => {
+                rule.month_day = Some(match value {
+                    "-1" => MonthDay::Last,
+                    _ => MonthDay::Day(
+                        value
+                            .parse()
+                            .map_err(|_| format!("unsupported BYMONTHDAY {value:?}"))?,
+                    ),
+                })
+            }
+            "count" => {
+                let count = value
+                    .parse()
+                    .map_err(|_| format!("invalid COUNT {value:?}"))?;
+                set_end(&mut rule, End::Count(count))?;
+            }
+            "until" => {
+                let date = value.get(..8).unwrap_or(value);
+                let date = NaiveDate::parse_from_str(date, "%Y%m%d")
+                    .map_err(|_| format!("invalid UNTIL {value:?}"))?;
+                set_end(&mut rule, End::Until(date))?;
+            }
+            "x-anchor" => {
+                rule.anchor = match value {
+                    "due" => Anchor::Due,
+                    "completion" => Anchor::Completion,
+                    _ => return Err(format!("invalid X-ANCHOR {value:?}")),
+                }
+            }
+            "wkst" => {}
+            _ => return Err(format!("unsupported RRULE part {}", key.to_uppercase())),
+        }
+    }
+
+    rule.frequency = frequency.ok_or("missing FREQ")?;
+    match by_day[..] {
+        [] => {}
+        [(Some(n), day)] if rule.month_day.is_none() => {
+            rule.month_day = Some(MonthDay::Nth(n, day))
+        }
+        _ if by_day.iter().all(|(n, _)| n.is_none()) => {
+            rule.weekdays = by_day.into_iter().map(|(_, day)| day).collect();
+            rule.weekdays.sort_by_key(Weekday::num_days_from_monday);
+            rule.weekdays.dedup();
+        }
+        _ => return Err("unsupported BYDAY combination".into()),
+    }
+    Ok(rule)
+}
+
+fn rrule_day(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_rrule_day(code: &str) -> Option<Weekday> {
+    [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ]
+    .into_iter()
+    .find(|&day| rrule_day(day).eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Recurrence {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_text_rules() {
+        assert_eq!(parse("Daily"), Recurrence::every(1, Frequency::Daily));
+        assert_eq!(
+            parse("every 2 weeks on mon,thu"),
+            Recurrence {
+                weekdays: vec![Weekday::Mon, Weekday::Thu],
+                ..Recurrence::every(2, Frequency::Weekly)
+            }
+        );
+        assert_eq!(
+            parse("every month on the last fri until 2026-12-31"),
+            Recurrence {
+                month_day: Some(MonthDay::Nth(-1, Weekday::Fri)),
+                end: Some(End::Until(NaiveDate::from_ymd_opt(2026, 12, 31).unwrap())),
+                ..Recurrence::every(1, Frequency::Monthly)
+            }
+        );
+        assert_eq!(
+            parse("every other month on the last day for 6 times"),
+            Recurrence {
+                month_day: Some(MonthDay::Last),
+                end: Some(End::Count(6)),
+                ..Recurrence::every(2, Frequency::Monthly)
+            }
+        );
+        assert_eq!(
+            parse("every year on the 2nd tuesday").month_day,
+            Some(MonthDay::Nth(2, Weekday::Tue))
+        );
+        assert_eq!(parse("every weekday").weekdays.len(), 5);
+        assert_eq!(
+            parse("every thursday and monday").to_string(),
+            "every week on mon,thu"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_or_meaningless_rules() {
+        for text in [
+            "monthly-ish",
+            "every 0 days",
+            "every fortnight",
+            "every day on mon",
+            "every week on the 15th",
+            "every month on the 32nd",
+            "every week until tomorrow",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;BYSETPOS=1",
+            "every 400000000 years",
+            "FREQ=WEEKLY;INTERVAL=4294967295",
+        ] {
+            assert!(text.parse::<Recurrence>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn rrule_and_text_forms_round_trip() {
+        for text in [
+            "every day",
+            "every 3 weeks on tue,sat 10 times",
+            "every month on the 15th",
+            "every 2 months on the 3rd wed until 2027-01-31",
+            "every year on the last day",
+            "every 3 days after completion 5 times",
+        ] {
+            let rule = parse(text);
+            assert_eq!(rule.to_string(), text);
+            assert_eq!(parse(&rule.to_rrule()), rule, "{}", rule.to_rrule());
+        }
+        assert_eq!(
+            parse("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;COUNT=4").to_string(),
+            "every 2 weeks on mon,thu 4 times"
+        );
+        assert_eq!(
+            parse("FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20261231T000000Z").to_rrule(),
+            "FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20261231"
+        );
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn next(rule: &str, after: &str) -> Option<String> {
+        parse(rule)
+            .next_after(date(after))
+            .map(|next| next.to_string())
+    }
+
+    #[test]
+    fn computes_next_dates() {
+        // 2026-10-15 is a Thursday.
+        assert_eq!(next("every 3 days", "2026-10-15").unwrap(), "2026-10-18");
+        assert_eq!(next("weekly", "2026-10-15").unwrap(), "2026-10-22");
+        assert_eq!(
+            next("every week on mon,fri", "2026-10-15").unwrap(),
+            "2026-10-16"
+        );
+        assert_eq!(
+            next("every 2 weeks on mon,thu", "2026-10-15").unwrap(),
+            "2026-10-26"
+        );
+        assert_eq!(next("monthly", "2026-01-31").unwrap(), "2026-02-28");
+        assert_eq!(
+            next("every month on the 20th", "2026-10-15").unwrap(),
+            "2026-10-20"
+        );
+        assert_eq!(
+            next("every month on the last day", "2026-10-31").unwrap(),
+            "2026-11-30"
+        );
+        assert_eq!(
+            next("every month on the last fri", "2026-10-30").unwrap(),
+            "2026-11-27"
+        );
+        assert_eq!(
+            next("every month on the 5th fri", "2026-10-30").unwrap(),
+            "2027-01-29"
+        );
+        assert_eq!(next("yearly", "2028-02-29").unwrap(), "2029-02-28");
+    }
+
+    #[test]
+    fn huge_intervals_end_the_rule_instead_of_overflowing() {
+        let date = date("2026-10-15");
+        for frequency in [
+            Frequency::Daily,
+            Frequency::Weekly,
+            Frequency::Monthly,
+            Frequency::Yearly,
+        ] {
+            let rule = Recurrence::every(u32::MAX, frequency);
+            assert_eq!(rule.next_after(date), None, "{frequency:?}");
+        }
+        assert_eq!(
+            parse("every 1000 years")
+                .next_after(date)
+                .unwrap()
+                .to_string(),
+            "3026-10-15"
+        );
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_rule() {
+        assert_eq!(next("daily until 2026-10-15", "2026-10-15"), None);
+        assert_eq!(next("daily 1 time", "2026-10-15"), None);
+
+        let rule = parse("daily 3 times");
+        assert_eq!(rule.advanced().end, Some(End::Count(2)));
+        assert_eq!(
+            rule.advanced().advanced().next_after(date("2026-10-15")),
+            None
+        );
+    }
+
+    #[test]
+    fn schedules_from_the_calendar_or_the_completion_date() {
+        let occurrence = |rule: &str, due: &str, done: &str| {
+            let (date, rule) = parse(rule).next_occurrence(date(due), date(done)).unwrap();
+            (date.to_string(), rule.end)
+        };
+
+        // Done on time or early: the next calendar date.
+        assert_eq!(
+            occurrence("weekly", "2026-10-15", "2026-10-14").0,
+            "2026-10-22"
+        );
+        // Three weeks late: missed dates are skipped, not piled up.
+        assert_eq!(
+            occurrence("weekly", "2026-10-01", "2026-10-20").0,
+            "2026-10-22"
+        );
+        assert_eq!(
+            occurrence("weekly 5 times", "2026-10-01", "2026-10-20"),
+            ("2026-10-22".into(), Some(End::Count(2)))
+        );
+        assert!(
+            parse("weekly 2 times")
+                .next_occurrence(date("2026-10-01"), date("2026-10-20"))
+                .is_none()
+        );
+
+        let after = "every 3 days after completion";
+        assert_eq!(
+            occurrence(after, "2026-10-01", "2026-10-20").0,
+            "2026-10-23"
+        );
+        assert_eq!(
+            occurrence(after, "2026-10-15", "2026-10-14").0,
+            "2026-10-17"
+        );
+    }
+
+    #[test]
+    fn upgrades_legacy_values() {
+        let weekly = Recurrence::from_legacy(&Value::from("Weekly")).unwrap();
+        assert_eq!(weekly, Recurrence::every(1, Frequency::Weekly));
+        let custom = serde_json::json!({ "Custom": "every 2 days" });
+        assert_eq!(
+            Recurrence::from_legacy(&custom).unwrap(),
+            Recurrence::every(2, Frequency::Daily)
+        );
+        let nonsense = serde_json::json!({ "Custom": "when I feel like it" });
+        assert_eq!(
+            Recurrence::from_legacy(&nonsense),
+            Err("when I feel like it".to_string())
+        );
     }
 }
@@ -0,0 +1,300 @@
+use crate::{
+    recurrence::{Recurrence, keep_unparsed},
+    todo::Todo,
+};
+use chrono::{DateTime, NaiveDateTime, NaiveTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Version written by this build. Bump it and append a step to
+/// [`MIGRATIONS`] whenever the on-disk representation of a todo changes.
+pub const CURRENT_VERSION: u64 = 5;
+
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// `MIGRATIONS[n]` upgrades the todos map from version `n + 1` to `n + 2`.
+const MIGRATIONS: &[Migration] = &[v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5];
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u64,
+    todos: &'a HashMap<Uuid, Todo>,
+}
+
+/// Serializes todos wrapped in a `{ "version": .., "todos": .. }` envelope.
+pub fn to_json(todos: &HashMap<Uuid, Todo>) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Envelope {
+        version: CURRENT_VERSION,
+        todos,
+    })
+}
+
+/// Parses a todos file of any known version, upgrading it in memory.
+///
+/// Version 1 files are the bare `{ "<uuid>": { .. } }` map written before
+/// the envelope existed.
+pub fn from_json(data: &str) -> Result<HashMap<Uuid, Todo>, String> {
+    let value: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+    let (version, mut todos) = match value {
+        Value::Object(mut envelope) if envelope.contains_key("version") => {
+            let version = envelope
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or("\"version\" must be a positive integer")?;
+            let todos = envelope.remove("todos").ok_or("missing \"todos\" field")?;
+            (version, todos)
+        }
+        bare => (1, bare),
+    };
+
+    todos = upgrade(todos, version)?;
+    serde_json::from_value(todos).map_err(|e| e.to_string())
+}
+
+fn upgrade(mut todos: Value, version: u64) -> Result<Value, String> {
+    if version == 0 || version > CURRENT_VERSION {
+        return Err(format!(
+            "unsupported file version {version} (this build reads up to {CURRENT_VERSION})"
+        ));
+    }
+    for migrate in &MIGRATIONS[(version - 1) as usize..] {
+        todos = migrate(todos)?;
+    }
+    Ok(todos)
+}
+
+/// Recovers every todo that still parses on its own from a damaged file,
+/// e.g. one truncated mid-write or with a single malformed entry.
+pub fn salvage(data: &str) -> Vec<Todo> {
+    let version = detect_version(data).unwrap_or(1);
+    let mut todos: HashMap<Uuid, Todo> = HashMap::new();
+    let mut pos = 0;
+
+    while let Some(offset) = data[pos..].find('{') {
+        let start = pos + offset;
+        let mut stream =
+            serde_json::Deserializer::from_str(&data[start..]).into_iter::<Map<String, Value>>();
+        if let Some(Ok(entry)) = stream.next()
+            && let Some(todo) = upgrade_entry(entry, version)
+        {
+            todos.entry(todo.id).or_insert(todo);
+            pos = start + stream.byte_offset();
+        } else {
+            pos = start + 1;
+        }
+    }
+
+    todos.into_values().collect()
+}
+
+fn upgrade_entry(entry: Map<String, Value>, version: u64) -> Option<Todo> {
+    let id = entry.get("id")?.as_str()?.to_string();
+    let todos = upgrade(Value::Object(Map::from_iter([(id, entry.into())])), version).ok()?;
+    serde_json::from_value::<HashMap<Uuid, Todo>>(todos)
+        .ok()?
+        .into_values()
+        .next()
+}
+
+/// Finds `"version": N` without requiring the rest of the file to parse.
+fn detect_version(data: &str) -> Option<u64> {
+    let rest = &data[data.find("\"version\"")? + "\"version\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// The envelope was introduced without changing the todo records.
+fn v1_to_v2(todos: Value) -> Result<Value, String> {
+    Ok(todos)
+}
+
+/// `created_at` was a naive UTC time and gains an explicit offset;
+/// `updated_at` is added, set to the latest timestamp the todo has.
+fn v2_to_v3(mut todos: Value) -> Result<Value, String> {
+    let entries = todos.as_object_mut().ok_or("todos must be an object")?;
+    for todo in entries.values_mut().filter_map(Value::as_object_mut) {
+        let Some(created) = todo.get("created_at").and_then(Value::as_str) else {
+            continue;
+        };
+        let created = parse_utc(created)?;
+        let updated = ["started_at", "completed_at"]
+            .iter()
+            .filter_map(|key| parse_utc(todo.get(*key)?.as_str()?).ok())
+            .fold(created, DateTime::max);
+        todo.insert("created_at".into(), created.to_rfc3339().into());
+        todo.entry("updated_at")
+            .or_insert_with(|| updated.to_rfc3339().into());
+    }
+    Ok(todos)
+}
+
+/// Recurrences became structured rules stored as RRULE text. Old custom
+/// text that is not a valid rule moves into the description.
+fn v3_to_v4(mut todos: Value) -> Result<Value, String> {
+    let entries = todos.as_object_mut().ok_or("todos must be an object")?;
+    for todo in entries.values_mut().filter_map(Value::as_object_mut) {
+        let Some(old) = todo.get("recurrence").filter(|r| !r.is_null()).cloned() else {
+            continue;
+        };
+        let rule = match Recurrence::from_legacy(&old) {
+            Ok(rule) => rule.to_rrule().into(),
+            Err(text) => {
+                let description = todo.get("description").and_then(Value::as_str);
+                let description = keep_unparsed(description, &text);
+                todo.insert("description".into(), description.into());
+                Value::Null
+            }
+        };
+        todo.insert("recurrence".into(), rule);
+    }
+    Ok(todos)
+}
+
+/// Due dates were always midnight UTC; they become all-day dates. Other
+/// times can only have come from editing the file and are kept.
+fn v4_to_v5(mut todos: Value) -> Result<Value, String> {
+    let entries = todos.as_object_mut().ok_or("todos must be an object")?;
+    for todo in entries.values_mut().filter_map(Value::as_object_mut) {
+        let Some(due) = todo.get("due_date").and_then(Value::as_str) else {
+            continue;
+        };
+        let due = parse_utc(due)?;
+        if due.time() == NaiveTime::MIN {
+            todo.insert(
+                "due_date".into(),
+                due.date_naive().format("%Y-%m-%d").to_string().into(),
+            );
+        }
+    }
+    Ok(todos)
+}
+
+/// Reads a timestamp with an offset, or a naive one taken to be UTC.
+fn parse_utc(text: &str) -> Result<DateTime<Utc>, String> {
+    text.parse::<DateTime<Utc>>()
+        .or_else(|_| text.parse::<NaiveDateTime>().map(|naive| naive.and_utc()))
+        .map_err(|e| format!("invalid timestamp {text:?}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dates::Zone, due::Due};
+    use chrono::NaiveDate;
+
+    const V1_FILE: &str = r#"{
+        "632eb08f-be4b-4550-a345-0f9cda860615": {
+            "id": "632eb08f-be4b-4550-a345-0f9cda860615",
+            "title": "Study Go",
+            "description": "Read the Go book",
+            "priority": "High",
+            "status": "Pending",
+            "created_at": "2025-09-03T12:58:03.529514352",
+            "due_date": null,
+            "tags": null,
+            "parent_id": null,
+            "subtasks": null,
+            "recurrence": null
+        }
+    }"#;
+
+    #[test]
+    fn reads_unversioned_files() {
+        let todos = from_json(V1_FILE).unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos.values().next().unwrap().title, "Study Go");
+    }
+
+    #[test]
+    fn adds_timezones_and_updated_at() {
+        let todo = from_json(V1_FILE).unwrap().into_values().next().unwrap();
+        assert_eq!(
+            todo.created_at.to_rfc3339(),
+            "2025-09-03T12:58:03.529514352+00:00"
+        );
+        assert_eq!(todo.updated_at, todo.created_at);
+
+        let completed = V1_FILE.replacen(
+            r#""due_date""#,
+            r#""completed_at": "2025-09-05T08:00:00Z", "due_date""#,
+            1,
+        );
+        let todo = from_json(&completed).unwrap().into_values().next().unwrap();
+        assert_eq!(todo.updated_at.to_rfc3339(), "2025-09-05T08:00:00+00:00");
+    }
+
+    #[test]
+    fn converts_old_recurrences_to_rules() {
+        let weekly = V1_FILE.replace(r#""recurrence": null"#, r#""recurrence": "Weekly""#);
+        let todo = from_json(&weekly).unwrap().into_values().next().unwrap();
+        assert_eq!(todo.recurrence.unwrap().to_string(), "every week");
+
+        let custom = V1_FILE.replace(
+            r#""recurrence": null"#,
+            r#""recurrence": { "Custom": "whenever" }"#,
+        );
+        let todo = from_json(&custom).unwrap().into_values().next().unwrap();
+        assert_eq!(todo.recurrence, None);
+        assert_eq!(
+            todo.description.as_deref(),
+            Some("Read the Go book\n\nRecurrence: whenever")
+        );
+    }
+
+    #[test]
+    fn turns_midnight_due_dates_into_all_day_ones() {
+        let due = |value: &str| {
+            let file = V1_FILE.replace(r#""due_date": null"#, &format!(r#""due_date": "{value}""#));
+            from_json(&file)
+                .unwrap()
+                .into_values()
+                .next()
+                .unwrap()
+                .due_date
+        };
+        assert_eq!(
+            due("2025-10-03T00:00:00Z"),
+            Some(Due::Date(NaiveDate::from_ymd_opt(2025, 10, 3).unwrap()))
+        );
+        assert_eq!(
+            due("2025-10-03T14:30:00Z").map(|due| due.start(Zone::Local).to_rfc3339()),
+            Some("2025-10-03T14:30:00+00:00".into())
+        );
+    }
+
+    #[test]
+    fn round_trips_current_version() {
+        let todos = from_json(V1_FILE).unwrap();
+        let json = to_json(&todos).unwrap();
+        assert!(json.contains(&format!("\"version\": {CURRENT_VERSION}")));
+        assert_eq!(from_json(&json).unwrap(), todos);
+    }
+
+    #[test]
+    fn salvages_entries_from_damaged_files() {
+        let todos = from_json(V1_FILE).unwrap();
+        let json = to_json(&todos).unwrap();
+        // Cut off the closing braces of the map and envelope.
+        let truncated = &json[..json.rfind('}').unwrap() - 4];
+        assert!(from_json(truncated).is_err());
+        assert_eq!(salvage(truncated).len(), 1);
+
+        let broken_entry =
+            V1_FILE.replacen("{", r#"{ "bad": { "id": "not-a-uuid", "title": 1 },"#, 1);
+        assert!(from_json(&broken_entry).is_err());
+        assert_eq!(
+            salvage(&broken_entry),
+            todos.into_values().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn rejects_newer_and_malformed_files() {
+        assert!(from_json(r#"{ "version": 99, "todos": {} }"#).is_err());
+        assert!(from_json(r#"{ "version": 2 }"#).is_err());
+        assert!(from_json("not json").is_err());
+    }
+}
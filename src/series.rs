@@ -0,0 +1,142 @@
+use crate::{dates::Zone, due::Due, status::Status, todo::Todo};
+use chrono::Utc;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Creates the next occurrence of the recurring todo `id` once it is Done.
+///
+/// The new todo copies everything but the dates and status, is due on the
+/// rule's next date (see
+/// [`next_occurrence`](crate::recurrence::Recurrence::next_occurrence); todos without a
+/// due date count from when they were done) and joins the same series. Its
+/// scheduled and wait-until dates move by as many days as the due date.
+/// Returns `None` when the todo does not recur, its rule has ended, or a
+/// later occurrence already exists, so
+/// reopening and finishing a todo again does not create duplicates.
+pub fn spawn_next(todos: &mut HashMap<Uuid, Todo>, id: Uuid) -> Option<Uuid> {
+    let todo = todos.get(&id)?;
+    if todo.status != Status::Done {
+        return None;
+    }
+    let rule = todo.recurrence.as_ref()?;
+    let series_id = todo.series_id.unwrap_or(todo.id);
+    let has_successor = todos.values().any(|other| {
+        other.id != id && other.series_id == Some(series_id) && other.created_at > todo.created_at
+    });
+    if has_successor {
+        return None;
+    }
+
+    let zone = Zone::current();
+    let done = zone
+        .local(todo.completed_at.unwrap_or_else(Utc::now))
+        .date();
+    let base = todo.due_date.map_or(done, |due| due.date(zone));
+    let (date, rule) = rule.next_occurrence(base, done)?;
+    let due = match todo.due_date {
+        Some(Due::At(at)) => Due::At(zone.utc(date.and_time(zone.local(at).time()))),
+        _ => Due::Date(date),
+    };
+    let shift = |date_of: Option<Due>| date_of.map(|d| d.shifted(date - base, zone));
+    let now = Utc::now();
+    let next = Todo {
+        id: Uuid::new_v4(),
+        status: Status::Pending,
+        created_at: now,
+        updated_at: now,
+        due_date: Some(due),
+        scheduled: shift(todo.scheduled),
+        wait_until: shift(todo.wait_until),
+        recurrence: Some(rule),
+        series_id: Some(series_id),
+        started_at: None,
+        completed_at: None,
+        ..todo.clone()
+    };
+    let next_id = next.id;
+    todos.insert(next_id, next);
+    if let Some(todo) = todos.get_mut(&id) {
+        todo.series_id = Some(series_id);
+    }
+    Some(next_id)
+}
+
+/// Every todo in the series `id` belongs to, oldest first. A todo that
+/// never recurred is a series of one.
+pub fn history(todos: &HashMap<Uuid, Todo>, id: Uuid) -> Vec<&Todo> {
+    let Some(todo) = todos.get(&id) else {
+        return Vec::new();
+    };
+    let series_id = todo.series_id.unwrap_or(id);
+    let mut members: Vec<&Todo> = todos
+        .values()
+        .filter(|t| t.id == series_id || t.series_id == Some(series_id))
+        .collect();
+    members.sort_by_key(|t| t.created_at);
+    members
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input::AddTodoInput, priority::Priority, recurrence::Recurrence};
+    use chrono::{DateTime, TimeZone};
+
+    fn weekly(todos: &mut HashMap<Uuid, Todo>, due: DateTime<Utc>) -> Uuid {
+        let todo = Todo::new(AddTodoInput {
+            priority: Priority::High,
+            due_date: Some(Due::At(due)),
+            wait_until: Some(Due::At(due - chrono::Duration::days(2))),
+            tags: Some(vec!["home".into()]),
+            recurrence: Some("weekly 2 times".parse::<Recurrence>().unwrap()),
+            ..AddTodoInput::new("Water plants")
+        });
+        let id = todo.id;
+        todos.insert(id, todo);
+        id
+    }
+
+    fn finish(todos: &mut HashMap<Uuid, Todo>, id: Uuid, at: DateTime<Utc>) {
+        let todo = todos.get_mut(&id).unwrap();
+        todo.set_status(Status::Done);
+        todo.completed_at = Some(at);
+    }
+
+    #[test]
+    fn completing_a_recurring_todo_creates_the_next_one() {
+        let mut todos = HashMap::new();
+        let due = Utc.with_ymd_and_hms(2026, 10, 15, 9, 30, 0).unwrap();
+        let first = weekly(&mut todos, due);
+
+        assert_eq!(spawn_next(&mut todos, first), None, "not done yet");
+        finish(&mut todos, first, due);
+        let second = spawn_next(&mut todos, first).unwrap();
+
+        let next = &todos[&second];
+        assert_eq!(next.status, Status::Pending);
+        assert_eq!(
+            next.due_date,
+            Some(Due::At(
+                Utc.with_ymd_and_hms(2026, 10, 22, 9, 30, 0).unwrap()
+            ))
+        );
+        assert_eq!(
+            next.wait_until,
+            Some(Due::At(
+                Utc.with_ymd_and_hms(2026, 10, 20, 9, 30, 0).unwrap()
+            ))
+        );
+        assert_eq!(next.priority, Priority::High);
+        assert_eq!(next.tags, Some(vec!["home".into()]));
+        assert_eq!(next.series_id, Some(first));
+        assert_eq!(todos[&first].series_id, Some(first));
+        assert_eq!(history(&todos, second).len(), 2);
+
+        // Finishing the same occurrence again does not add another one.
+        assert_eq!(spawn_next(&mut todos, first), None);
+
+        // The rule allowed two occurrences in total.
+        finish(&mut todos, second, due);
+        assert_eq!(spawn_next(&mut todos, second), None);
+    }
+}
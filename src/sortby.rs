@@ -1,11 +1,14 @@
 use clap::ValueEnum;
 use std::fmt;
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum SortBy {
     Priority,
     Status,
     Created,
+    Updated,
+    Started,
+    Completed,
     DueDate,
     Overdue,
 }
@@ -16,6 +19,9 @@ impl fmt::Display for SortBy {
             SortBy::Priority => "Priority",
             SortBy::Status => "Status",
             SortBy::Created => "Created Date",
+            SortBy::Updated => "Last Updated",
+            SortBy::Started => "Start Date",
+            SortBy::Completed => "Completion Date",
             SortBy::DueDate => "Due Date",
             SortBy::Overdue => "Overdue",
         };
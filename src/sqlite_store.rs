@@ -0,0 +1,773 @@
+use crate::{
+    dates::Zone,
+    due::Due,
+    error::{TodoError, TodoResult},
+    ids::IdIndex,
+    recurrence::{Recurrence, keep_unparsed},
+    storage::{TodoQuery, TodoStore},
+    todo::Todo,
+};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use rusqlite::{
+    Connection, OptionalExtension, Row, Transaction, TransactionBehavior, params, params_from_iter,
+    types::{FromSqlError, Type},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use std::{collections::HashMap, path::Path, time::Duration};
+use uuid::Uuid;
+
+/// One step of [`MIGRATIONS`]: plain SQL, or code for changes SQL cannot
+/// express.
+enum Migration {
+    Sql(&'static str),
+    Code(fn(&Transaction) -> rusqlite::Result<()>),
+}
+
+/// `MIGRATIONS[n]` brings a database from `PRAGMA user_version` n to n + 1.
+/// Databases created before versioning report 0 and already have the
+/// tables, which is why the first step only creates what is missing.
+const MIGRATIONS: &[Migration] = &[
+    Migration::Sql(
+        "
+    CREATE TABLE IF NOT EXISTS todos (
+        id          TEXT PRIMARY KEY,
+        title       TEXT NOT NULL,
+        description TEXT,
+        priority    TEXT NOT NULL,
+        status      TEXT NOT NULL,
+        created_at  TEXT NOT NULL,
+        due_date    TEXT,
+        parent_id   TEXT,
+        recurrence  TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS todo_tags (
+        todo_id  TEXT NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        tag      TEXT NOT NULL,
+        PRIMARY KEY (todo_id, position)
+    );
+
+    CREATE TABLE IF NOT EXISTS todo_subtasks (
+        todo_id    TEXT NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+        position   INTEGER NOT NULL,
+        subtask_id TEXT NOT NULL,
+        PRIMARY KEY (todo_id, position)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_todos_status ON todos(status);
+    CREATE INDEX IF NOT EXISTS idx_todos_priority ON todos(priority);
+    CREATE INDEX IF NOT EXISTS idx_todos_due_date ON todos(due_date);
+    CREATE INDEX IF NOT EXISTS idx_todos_parent_id ON todos(parent_id);
+    CREATE INDEX IF NOT EXISTS idx_todo_tags_tag ON todo_tags(tag);
+    ",
+    ),
+    Migration::Sql(
+        "
+    ALTER TABLE todos ADD COLUMN started_at TEXT;
+    ALTER TABLE todos ADD COLUMN completed_at TEXT;
+    ",
+    ),
+    Migration::Sql(
+        "
+    UPDATE todos SET created_at = created_at || '+00:00'
+        WHERE created_at NOT LIKE '%+__:__' AND created_at NOT LIKE '%Z';
+    ALTER TABLE todos ADD COLUMN updated_at TEXT;
+    UPDATE todos SET updated_at =
+        max(created_at, COALESCE(started_at, ''), COALESCE(completed_at, ''));
+    CREATE INDEX IF NOT EXISTS idx_todos_updated_at ON todos(updated_at);
+    ",
+    ),
+    Migration::Code(upgrade_recurrences),
+    Migration::Sql(
+        "
+    ALTER TABLE todos ADD COLUMN series_id TEXT;
+    CREATE INDEX IF NOT EXISTS idx_todos_series_id ON todos(series_id);
+    ",
+    ),
+    // Due dates used to be forced to midnight UTC; those are all-day.
+    Migration::Sql(
+        "
+    UPDATE todos SET due_date = substr(due_date, 1, 10)
+        WHERE substr(due_date, 11) IN (' 00:00:00+00:00', ' 00:00:00');
+    ",
+    ),
+    Migration::Sql(
+        "
+    ALTER TABLE todos ADD COLUMN scheduled TEXT;
+    ALTER TABLE todos ADD COLUMN wait_until TEXT;
+    ",
+    ),
+];
+
+const SELECT_TODOS: &str = "SELECT id, title, description, priority, status, created_at, \
+                            due_date, parent_id, recurrence, started_at, completed_at, \
+                            updated_at, series_id, scheduled, wait_until FROM todos";
+
+/// Stores todos in an embedded SQLite database.
+///
+/// Tags and subtasks live in their own tables, so an empty list is read
+/// back as `None`.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> TodoResult<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> TodoResult<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> TodoResult<Self> {
+        // Wait for other `todo` processes instead of failing with SQLITE_BUSY.
+        conn.busy_timeout(Duration::from_secs(10))?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        migrate(&mut conn)?;
+        Ok(Self { conn })
+    }
+
+    /// Copies every todo from `source` into this database, replacing any
+    /// todo with the same ID. Returns the number of todos imported.
+    pub fn import_from(&mut self, source: &dyn TodoStore) -> TodoResult<usize> {
+        let todos = source.load()?;
+        let tx = self.conn.transaction()?;
+        for todo in todos.values() {
+            write_todo(&tx, todo)?;
+        }
+        tx.commit()?;
+        Ok(todos.len())
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> rusqlite::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut todos = stmt
+            .query_map(params, todo_from_row)?
+            .collect::<rusqlite::Result<Vec<Todo>>>()?;
+        for todo in &mut todos {
+            todo.tags = self.tags_for(todo.id)?;
+            todo.subtasks = self.subtasks_for(todo.id)?;
+        }
+        Ok(todos)
+    }
+
+    fn tags_for(&self, id: Uuid) -> rusqlite::Result<Option<Vec<String>>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT tag FROM todo_tags WHERE todo_id = ?1 ORDER BY position")?;
+        let tags = stmt
+            .query_map(params![id.to_string()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(non_empty(tags))
+    }
+
+    fn subtasks_for(&self, id: Uuid) -> rusqlite::Result<Option<Vec<Uuid>>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT subtask_id FROM todo_subtasks WHERE todo_id = ?1 ORDER BY position",
+        )?;
+        let subtasks = stmt
+            .query_map(params![id.to_string()], |row| uuid_column(row, 0))?
+            .collect::<rusqlite::Result<Vec<Uuid>>>()?;
+        Ok(non_empty(subtasks))
+    }
+
+    fn load_all(conn: &Connection) -> rusqlite::Result<HashMap<Uuid, Todo>> {
+        Self::load_where(conn, None, &[])
+    }
+
+    /// The todos whose rows match the SQL `condition`, or all of them, with
+    /// their tags and subtasks. `values` fill the condition's `?`s.
+    fn load_where(
+        conn: &Connection,
+        condition: Option<&str>,
+        values: &[String],
+    ) -> rusqlite::Result<HashMap<Uuid, Todo>> {
+        let (todo_filter, link_filter) = match condition {
+            Some(condition) => (
+                format!("WHERE {condition}"),
+                format!("WHERE todo_id IN (SELECT id FROM todos WHERE {condition})"),
+            ),
+            None => Default::default(),
+        };
+        let mut todos = conn
+            .prepare(&format!("{SELECT_TODOS} {todo_filter}"))?
+            .query_map(params_from_iter(values), todo_from_row)?
+            .map(|todo| todo.map(|t| (t.id, t)))
+            .collect::<rusqlite::Result<HashMap<Uuid, Todo>>>()?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT todo_id, tag FROM todo_tags {link_filter} ORDER BY todo_id, position"
+        ))?;
+        let mut rows = stmt.query(params_from_iter(values))?;
+        while let Some(row) = rows.next()? {
+            if let Some(todo) = todos.get_mut(&uuid_column(row, 0)?) {
+                todo.tags.get_or_insert_with(Vec::new).push(row.get(1)?);
+            }
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT todo_id, subtask_id FROM todo_subtasks {link_filter} \
+             ORDER BY todo_id, position"
+        ))?;
+        let mut rows = stmt.query(params_from_iter(values))?;
+        while let Some(row) = rows.next()? {
+            if let Some(todo) = todos.get_mut(&uuid_column(row, 0)?) {
+                todo.subtasks
+                    .get_or_insert_with(Vec::new)
+                    .push(uuid_column(row, 1)?);
+            }
+        }
+
+        Ok(todos)
+    }
+
+    fn commit_changes(
+        &mut self,
+        f: &mut dyn FnMut(&mut HashMap<Uuid, Todo>) -> bool,
+    ) -> rusqlite::Result<bool> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let before = Self::load_all(&tx)?;
+        let mut after = before.clone();
+        if !f(&mut after) {
+            return Ok(false);
+        }
+
+        for id in before.keys().filter(|id| !after.contains_key(id)) {
+            tx.execute("DELETE FROM todos WHERE id = ?1", params![id.to_string()])?;
+        }
+        for todo in after.values() {
+            if before.get(&todo.id) != Some(todo) {
+                write_todo(&tx, todo)?;
+            }
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Writes `todo` alone if its row exists exactly when `exists` says so.
+    fn write_one(&mut self, todo: &Todo, exists: bool) -> rusqlite::Result<bool> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let found = tx
+            .query_row(
+                "SELECT 1 FROM todos WHERE id = ?1",
+                params![todo.id.to_string()],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if found != exists {
+            return Ok(false);
+        }
+        write_todo(&tx, todo)?;
+        tx.commit()?;
+        Ok(true)
+    }
+}
+
+impl TodoStore for SqliteStore {
+    fn load(&self) -> TodoResult<HashMap<Uuid, Todo>> {
+        Ok(Self::load_all(&self.conn)?)
+    }
+
+    fn transaction(
+        &mut self,
+        f: &mut dyn FnMut(&mut HashMap<Uuid, Todo>) -> bool,
+    ) -> TodoResult<bool> {
+        Ok(self.commit_changes(f)?)
+    }
+
+    fn get(&self, id: Uuid) -> TodoResult<Option<Todo>> {
+        self.query(
+            &format!("{SELECT_TODOS} WHERE id = ?1"),
+            params![id.to_string()],
+        )
+        .map(|mut todos| todos.pop())
+        .map_err(TodoError::from)
+    }
+
+    /// Narrows the rows with the indexes on status, priority and due date,
+    /// then checks the exact conditions on the todos read.
+    fn find(&self, query: &TodoQuery) -> TodoResult<HashMap<Uuid, Todo>> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(status) = &query.status {
+            conditions.push("status = ?");
+            values.push(enum_to_text(status));
+        }
+        if let Some(priority) = &query.priority {
+            conditions.push("priority = ?");
+            values.push(enum_to_text(priority));
+        }
+        // Stored due dates sort by their text, so comparing with a day two
+        // days after the bound keeps every todo due before it in any zone.
+        if let Some(cutoff) = query.due_before.and_then(|bound| {
+            bound
+                .start(Zone::current())
+                .checked_add_signed(TimeDelta::days(2))
+        }) {
+            conditions.push("due_date < ?");
+            values.push(cutoff.format("%F").to_string());
+        }
+        let condition = (!conditions.is_empty()).then(|| conditions.join(" AND "));
+        let mut todos = Self::load_where(&self.conn, condition.as_deref(), &values)?;
+        todos.retain(|_, todo| query.matches(todo));
+        Ok(todos)
+    }
+
+    fn id_index(&self) -> TodoResult<IdIndex> {
+        let entries = self
+            .conn
+            .prepare("SELECT id, created_at, title FROM todos")?
+            .query_map([], |row| {
+                Ok((uuid_column(row, 0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(IdIndex::from_entries(entries))
+    }
+
+    fn insert(&mut self, todo: Todo) -> TodoResult<bool> {
+        Ok(self.write_one(&todo, false)?)
+    }
+
+    fn update(&mut self, todo: Todo) -> TodoResult<bool> {
+        Ok(self.write_one(&todo, true)?)
+    }
+
+    fn delete(&mut self, id: Uuid) -> TodoResult<bool> {
+        self.conn
+            .execute("DELETE FROM todos WHERE id = ?1", params![id.to_string()])
+            .map(|deleted| deleted > 0)
+            .map_err(TodoError::from)
+    }
+}
+
+/// Runs the migrations the database has not had yet. Refuses databases
+/// from a newer version rather than reading a schema it does not know.
+fn migrate(conn: &mut Connection) -> TodoResult<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: usize = tx.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(TodoError::Unsupported(format!(
+            "the database was created by a newer version of todo (schema {version}, \
+             this version knows up to {}); upgrade todo to open it",
+            MIGRATIONS.len()
+        )));
+    }
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        match migration {
+            Migration::Sql(sql) => tx.execute_batch(sql)?,
+            Migration::Code(upgrade) => upgrade(&tx)?,
+        }
+        tx.pragma_update(None, "user_version", step + 1)?;
+    }
+    Ok(tx.commit()?)
+}
+
+/// Rewrites recurrences stored as `"Daily"`, `"Weekly"` or `{"Custom": ..}`
+/// as rules. Custom text that is not a valid rule moves to the description.
+fn upgrade_recurrences(tx: &Transaction) -> rusqlite::Result<()> {
+    let rows = tx
+        .prepare("SELECT id, recurrence, description FROM todos WHERE recurrence IS NOT NULL")?
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, recurrence, description) in rows {
+        let old: serde_json::Value =
+            serde_json::from_str(&recurrence).map_err(|e| conversion_error(1, e))?;
+        match Recurrence::from_legacy(&old) {
+            Ok(rule) => tx.execute(
+                "UPDATE todos SET recurrence = ?2 WHERE id = ?1",
+                params![
+                    id,
+                    serde_json::to_string(&rule).expect("rules are serializable")
+                ],
+            )?,
+            Err(text) => tx.execute(
+                "UPDATE todos SET recurrence = NULL, description = ?2 WHERE id = ?1",
+                params![id, keep_unparsed(description.as_deref(), &text)],
+            )?,
+        };
+    }
+    Ok(())
+}
+
+fn write_todo(tx: &Transaction, todo: &Todo) -> rusqlite::Result<()> {
+    let id = todo.id.to_string();
+    let recurrence = todo
+        .recurrence
+        .as_ref()
+        .map(|r| serde_json::to_string(r).expect("recurrence is serializable"));
+
+    let exists = tx
+        .query_row("SELECT 1 FROM todos WHERE id = ?1", params![id], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if exists {
+        tx.execute(
+            "UPDATE todos SET title = ?2, description = ?3, priority = ?4, status = ?5, \
+             created_at = ?6, due_date = ?7, parent_id = ?8, recurrence = ?9, \
+             started_at = ?10, completed_at = ?11, updated_at = ?12, series_id = ?13, \
+             scheduled = ?14, wait_until = ?15 WHERE id = ?1",
+            params![
+                id,
+                todo.title,
+                todo.description,
+                enum_to_text(&todo.priority),
+                enum_to_text(&todo.status),
+                todo.created_at,
+                todo.due_date.map(due_to_text),
+                todo.parent_id.map(|p| p.to_string()),
+                recurrence,
+                todo.started_at,
+                todo.completed_at,
+                todo.updated_at,
+                todo.series_id.map(|s| s.to_string()),
+                todo.scheduled.map(due_to_text),
+                todo.wait_until.map(due_to_text),
+            ],
+        )?;
+    } else {
+        tx.execute(
+            "INSERT INTO todos (id, title, description, priority, status, created_at, \
+             due_date, parent_id, recurrence, started_at, completed_at, updated_at, series_id, \
+             scheduled, wait_until) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                id,
+                todo.title,
+                todo.description,
+                enum_to_text(&todo.priority),
+                enum_to_text(&todo.status),
+                todo.created_at,
+                todo.due_date.map(due_to_text),
+                todo.parent_id.map(|p| p.to_string()),
+                recurrence,
+                todo.started_at,
+                todo.completed_at,
+                todo.updated_at,
+                todo.series_id.map(|s| s.to_string()),
+                todo.scheduled.map(due_to_text),
+                todo.wait_until.map(due_to_text),
+            ],
+        )?;
+    }
+
+    tx.execute("DELETE FROM todo_tags WHERE todo_id = ?1", params![id])?;
+    for (position, tag) in todo.tags.iter().flatten().enumerate() {
+        tx.execute(
+            "INSERT INTO todo_tags (todo_id, position, tag) VALUES (?1, ?2, ?3)",
+            params![id, position as i64, tag],
+        )?;
+    }
+
+    tx.execute("DELETE FROM todo_subtasks WHERE todo_id = ?1", params![id])?;
+    for (position, subtask) in todo.subtasks.iter().flatten().enumerate() {
+        tx.execute(
+            "INSERT INTO todo_subtasks (todo_id, position, subtask_id) VALUES (?1, ?2, ?3)",
+            params![id, position as i64, subtask.to_string()],
+        )?;
+    }
+    Ok(())
+}
+
+fn todo_from_row(row: &Row) -> rusqlite::Result<Todo> {
+    let recurrence: Option<String> = row.get(8)?;
+    Ok(Todo {
+        id: uuid_column(row, 0)?,
+        title: row.get(1)?,
+        description: row.get(2)?,
+        priority: enum_column(row, 3)?,
+        status: enum_column(row, 4)?,
+        created_at: row.get(5)?,
+        due_date: due_column(row, 6)?,
+        scheduled: due_column(row, 13)?,
+        wait_until: due_column(row, 14)?,
+        tags: None,
+        parent_id: row
+            .get::<_, Option<String>>(7)?
+            .map(|pid| parse_uuid(7, &pid))
+            .transpose()?,
+        subtasks: None,
+        recurrence: recurrence
+            .map(|r| serde_json::from_str::<Recurrence>(&r))
+            .transpose()
+            .map_err(|e| conversion_error(8, e))?,
+        started_at: row.get(9)?,
+        completed_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        series_id: row
+            .get::<_, Option<String>>(12)?
+            .map(|sid| parse_uuid(12, &sid))
+            .transpose()?,
+    })
+}
+
+/// All-day due dates are stored as `YYYY-MM-DD`, timed ones like other
+/// timestamps, so both sort by their text.
+fn due_to_text(due: Due) -> String {
+    match due {
+        Due::Date(date) => date.format("%F").to_string(),
+        Due::At(at) => at.format("%F %T%.f%:z").to_string(),
+    }
+}
+
+fn due_column(row: &Row, idx: usize) -> rusqlite::Result<Option<Due>> {
+    let Some(text) = row.get::<_, Option<String>>(idx)? else {
+        return Ok(None);
+    };
+    if let Ok(date) = NaiveDate::parse_from_str(&text, "%F") {
+        return Ok(Some(Due::Date(date)));
+    }
+    let at: DateTime<Utc> = row.get(idx)?;
+    Ok(Some(Due::At(at)))
+}
+
+fn uuid_column(row: &Row, idx: usize) -> rusqlite::Result<Uuid> {
+    parse_uuid(idx, &row.get::<_, String>(idx)?)
+}
+
+fn parse_uuid(idx: usize, text: &str) -> rusqlite::Result<Uuid> {
+    Uuid::parse_str(text).map_err(|e| conversion_error(idx, e))
+}
+
+/// Priority and status are stored by their serde variant name so the
+/// database stays readable and matches the JSON file.
+fn enum_to_text<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(text)) => text,
+        _ => unreachable!("unit enum variants serialize to strings"),
+    }
+}
+
+fn enum_column<T: DeserializeOwned>(row: &Row, idx: usize) -> rusqlite::Result<T> {
+    serde_json::from_value(serde_json::Value::String(row.get(idx)?))
+        .map_err(|e| conversion_error(idx, e))
+}
+
+fn conversion_error(
+    idx: usize,
+    error: impl std::error::Error + Send + Sync + 'static,
+) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(
+        idx,
+        Type::Text,
+        Box::new(FromSqlError::Other(Box::new(error))),
+    )
+}
+
+fn non_empty<T>(items: Vec<T>) -> Option<Vec<T>> {
+    if items.is_empty() { None } else { Some(items) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input::AddTodoInput, priority::Priority, status::Status, storage::MemoryStore};
+    use chrono::Duration;
+
+    fn sample_todo(title: &str, status: Status) -> Todo {
+        let mut todo = Todo::new(AddTodoInput {
+            description: Some("details".into()),
+            priority: Priority::High,
+            status,
+            due_date: Some(Due::At(Utc::now() - Duration::days(1))),
+            scheduled: NaiveDate::from_ymd_opt(2026, 10, 20).map(Due::Date),
+            wait_until: Some(Due::At(Utc::now() + Duration::days(1))),
+            tags: Some(vec!["work".into(), "urgent".into()]),
+            recurrence: Some("every month on the last fri".parse().unwrap()),
+            parent_id: Some(Uuid::new_v4()),
+            subtasks: Some(vec![Uuid::new_v4(), Uuid::new_v4()]),
+            ..AddTodoInput::new(title)
+        });
+        todo.series_id = Some(Uuid::new_v4());
+        todo
+    }
+
+    #[test]
+    fn upgrades_unversioned_databases() {
+        let conn = Connection::open_in_memory().unwrap();
+        let Migration::Sql(schema) = MIGRATIONS[0] else {
+            unreachable!("the first step creates the tables")
+        };
+        conn.execute_batch(schema).unwrap();
+        let (old_id, custom_id) = (Uuid::new_v4(), Uuid::new_v4());
+        conn.execute(
+            "INSERT INTO todos (id, title, priority, status, created_at, recurrence, due_date) \
+             VALUES (?1, 'Old', 'Low', 'Done', '2025-01-01 09:30:00.250', '\"Daily\"', \
+                     '2025-03-01 00:00:00+00:00'), \
+                    (?2, 'Custom', 'Low', 'Pending', '2025-01-02 10:00:00', \
+                     '{\"Custom\":\"on full moons\"}', '2025-03-01 14:30:00+00:00')",
+            params![old_id.to_string(), custom_id.to_string()],
+        )
+        .unwrap();
+
+        let store = SqliteStore::with_connection(conn).unwrap();
+        let todos = store.load().unwrap();
+        let old = &todos[&old_id];
+        assert_eq!(old.title, "Old");
+        assert_eq!(old.recurrence.as_ref().unwrap().to_string(), "every day");
+        assert_eq!(todos[&custom_id].recurrence, None);
+        assert_eq!(
+            todos[&custom_id].description.as_deref(),
+            Some("Recurrence: on full moons")
+        );
+        assert_eq!(old.completed_at, None);
+        assert_eq!(
+            old.due_date,
+            Some(Due::Date(NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()))
+        );
+        assert_eq!(
+            todos[&custom_id]
+                .due_date
+                .map(|due| due.start(Zone::Local).to_rfc3339()),
+            Some("2025-03-01T14:30:00+00:00".into())
+        );
+        assert_eq!(old.created_at.to_rfc3339(), "2025-01-01T09:30:00.250+00:00");
+        assert_eq!(old.updated_at, old.created_at);
+        let stored: String = store
+            .conn
+            .query_row(
+                "SELECT created_at FROM todos WHERE id = ?1",
+                params![old_id.to_string()],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(stored, "2025-01-01 09:30:00.250+00:00");
+
+        let version: usize = store
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn refuses_databases_from_a_newer_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+
+        let error = SqliteStore::with_connection(conn).err().unwrap();
+        assert!(matches!(error, TodoError::Unsupported(_)));
+        assert!(error.to_string().contains("newer version"));
+    }
+
+    #[test]
+    fn round_trips_all_fields() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let todo = sample_todo("Ship release", Status::Pending);
+
+        assert!(store.insert(todo.clone()).unwrap());
+        assert_eq!(store.get(todo.id).unwrap(), Some(todo.clone()));
+        assert_eq!(store.load().unwrap().remove(&todo.id), Some(todo));
+    }
+
+    #[test]
+    fn transaction_updates_and_deletes() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let keep = sample_todo("Keep", Status::Pending);
+        let drop = sample_todo("Drop", Status::Pending);
+        store.insert(keep.clone()).unwrap();
+        store.insert(drop.clone()).unwrap();
+
+        store
+            .transaction(&mut |todos| {
+                todos.remove(&drop.id);
+                todos.get_mut(&keep.id).unwrap().tags = Some(vec!["home".into()]);
+                true
+            })
+            .unwrap();
+
+        let todos = store.load().unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[&keep.id].tags, Some(vec!["home".into()]));
+    }
+
+    #[test]
+    fn insert_and_update_check_whether_the_todo_exists() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let mut todo = sample_todo("Write", Status::Pending);
+        assert!(!store.update(todo.clone()).unwrap());
+        assert!(store.insert(todo.clone()).unwrap());
+        assert!(!store.insert(todo.clone()).unwrap());
+
+        todo.tags = Some(vec!["home".into()]);
+        assert!(store.update(todo.clone()).unwrap());
+        assert_eq!(store.get(todo.id).unwrap(), Some(todo));
+    }
+
+    #[test]
+    fn indexed_queries_match_the_in_memory_filter() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let pending = sample_todo("Pending", Status::Pending);
+        let mut done = sample_todo("Done", Status::Done);
+        let mut later = sample_todo("Later", Status::Pending);
+        later.priority = Priority::Low;
+        later.due_date = NaiveDate::from_ymd_opt(2099, 1, 1).map(Due::Date);
+        later.created_at = pending.created_at + Duration::minutes(2);
+        done.created_at = pending.created_at + Duration::minutes(1);
+        let memory = MemoryStore::from(HashMap::from([
+            (pending.id, pending.clone()),
+            (done.id, done.clone()),
+            (later.id, later.clone()),
+        ]));
+        store.import_from(&memory).unwrap();
+
+        let today = Some(Due::Date(Zone::current().today()));
+        for query in [
+            TodoQuery::default(),
+            TodoQuery {
+                status: Some(Status::Done),
+                ..Default::default()
+            },
+            TodoQuery {
+                priority: Some(Priority::High),
+                ..Default::default()
+            },
+            TodoQuery {
+                status: Some(Status::Pending),
+                due_before: today,
+                ..Default::default()
+            },
+        ] {
+            assert_eq!(store.find(&query).unwrap(), memory.find(&query).unwrap());
+        }
+        assert_eq!(store.find(&TodoQuery::default()).unwrap().len(), 3);
+
+        let ids = store.id_index().unwrap();
+        assert_eq!(ids.number(pending.id), Some(1));
+        assert_eq!(ids.number(later.id), Some(3));
+    }
+
+    #[test]
+    fn queries_use_the_indexes() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        for (sql, index) in [
+            ("status = 'Done'", "idx_todos_status"),
+            ("priority = 'High'", "idx_todos_priority"),
+            ("due_date < '2026-01-01'", "idx_todos_due_date"),
+        ] {
+            let plan: String = store
+                .conn
+                .query_row(
+                    &format!("EXPLAIN QUERY PLAN {SELECT_TODOS} WHERE {sql}"),
+                    [],
+                    |row| row.get(3),
+                )
+                .unwrap();
+            assert!(plan.contains(index), "{plan}");
+        }
+    }
+}
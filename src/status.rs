@@ -1,21 +1,268 @@
-use clap::ValueEnum;
-use serde::{Deserialize, Serialize};
-use std::fmt::{Display, Formatter, Result};
+use crate::{
+    config::Config,
+    error::{TodoError, TodoResult},
+};
+use colored::{Color, ColoredString, Colorize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+/// Where a todo is in its workflow. Besides the built-in states, the config
+/// file can define custom ones under `[[statuses]]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Status {
     Pending,
     InProgress,
+    Blocked,
+    Waiting,
     Done,
+    Cancelled,
+    Custom(String),
+}
+
+impl Status {
+    pub const BUILT_IN: [Status; 6] = [
+        Status::Pending,
+        Status::InProgress,
+        Status::Blocked,
+        Status::Waiting,
+        Status::Done,
+        Status::Cancelled,
+    ];
+
+    /// The spelling used on the command line and in the config file.
+    pub fn name(&self) -> &str {
+        match self {
+            Status::Pending => "pending",
+            Status::InProgress => "in-progress",
+            Status::Blocked => "blocked",
+            Status::Waiting => "waiting",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+            Status::Custom(name) => name,
+        }
+    }
+
+    /// Whether `name` refers to this status, ignoring case and punctuation.
+    pub fn matches(&self, name: &str) -> bool {
+        normalize(self.name()) == normalize(name)
+    }
+
+    /// Closed todos are finished: Done, Cancelled and custom statuses
+    /// configured with `closed = true`.
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Status::Done | Status::Cancelled => true,
+            Status::Custom(name) => Config::current()
+                .custom_status(name)
+                .is_some_and(|custom| custom.closed),
+            _ => false,
+        }
+    }
+
+    /// Colors `text` the way this status is shown everywhere. The theme's
+    /// color wins over the built-in one and a custom status's own.
+    pub fn paint(&self, text: &str) -> ColoredString {
+        if let Some(color) = Config::current().theme.status_color(self) {
+            return text.color(color);
+        }
+        match self {
+            Status::Pending => text.red().bold(),
+            Status::InProgress => text.yellow(),
+            Status::Blocked => text.magenta().bold(),
+            Status::Waiting => text.blue(),
+            Status::Done => text.green(),
+            Status::Cancelled => text.dimmed(),
+            Status::Custom(name) => match Config::current()
+                .custom_status(name)
+                .and_then(|custom| custom.color.as_deref())
+                .and_then(|color| color.parse::<Color>().ok())
+            {
+                Some(color) => text.color(color),
+                None => text.normal(),
+            },
+        }
+    }
+
+    /// Checks a status typed by the user against the config. Custom
+    /// statuses come back in their configured spelling.
+    pub fn validate(self) -> TodoResult<Self> {
+        match self {
+            Status::Custom(name) => Config::current()
+                .custom_status(&name)
+                .map(|custom| Status::Custom(custom.name.clone()))
+                .ok_or(TodoError::UnknownStatus(name)),
+            built_in => Ok(built_in),
+        }
+    }
+
+    /// Fails if the `[transitions]` table does not allow moving from this
+    /// status to `to`. Keeping the same status is always allowed.
+    pub fn check_transition(&self, to: &Status) -> TodoResult<()> {
+        if Config::current().allows_transition(self, to) {
+            Ok(())
+        } else {
+            Err(TodoError::Transition {
+                from: self.clone(),
+                to: to.clone(),
+            })
+        }
+    }
+
+    /// Sort position: open statuses in workflow order, then closed ones.
+    /// Custom statuses follow the built-ins of their kind in config order.
+    fn rank(&self) -> (u8, usize) {
+        match self {
+            Status::Pending => (0, 0),
+            Status::InProgress => (1, 0),
+            Status::Blocked => (2, 0),
+            Status::Waiting => (3, 0),
+            Status::Done => (5, 0),
+            Status::Cancelled => (6, 0),
+            Status::Custom(_) => {
+                let statuses = &Config::current().statuses;
+                match statuses
+                    .iter()
+                    .position(|custom| self.matches(&custom.name))
+                {
+                    Some(i) if statuses[i].closed => (7, i),
+                    Some(i) => (4, i),
+                    None => (8, 0),
+                }
+            }
+        }
+    }
+
+    /// Name stored in data files; built-ins keep their original variant
+    /// names so existing files stay readable.
+    fn stored_name(&self) -> &str {
+        match self {
+            Status::Pending => "Pending",
+            Status::InProgress => "InProgress",
+            Status::Blocked => "Blocked",
+            Status::Waiting => "Waiting",
+            Status::Done => "Done",
+            Status::Cancelled => "Cancelled",
+            Status::Custom(name) => name,
+        }
+    }
+}
+
+/// Lowercases `name` and drops everything but letters and digits, so
+/// `In Progress`, `in_progress` and `InProgress` compare equal.
+pub fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
 }
 
 impl Display for Status {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let label = match self {
             Status::Pending => "Pending",
             Status::InProgress => "In Progress",
+            Status::Blocked => "Blocked",
+            Status::Waiting => "Waiting",
             Status::Done => "Done",
+            Status::Cancelled => "Cancelled",
+            Status::Custom(name) => name,
         };
         write!(f, "{label}")
     }
 }
+
+/// Accepts any built-in spelling (`in-progress`, `In Progress`,
+/// `InProgress`); other names become custom statuses, to be checked with
+/// [`Status::validate`] once the config is loaded.
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        if normalize(input).is_empty() {
+            return Err("status names need at least one letter or digit".into());
+        }
+        Ok(Status::BUILT_IN
+            .into_iter()
+            .find(|status| status.matches(input))
+            .unwrap_or_else(|| Status::Custom(input.to_string())))
+    }
+}
+
+impl Ord for Status {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank()
+            .cmp(&other.rank())
+            .then_with(|| self.name().cmp(other.name()))
+    }
+}
+
+impl PartialOrd for Status {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Serialize for Status {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.stored_name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Status {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_built_in_spellings_and_custom_names() {
+        for input in ["in-progress", "In Progress", "InProgress", "in_progress"] {
+            assert_eq!(input.parse::<Status>().unwrap(), Status::InProgress);
+        }
+        assert_eq!(
+            "Review".parse::<Status>().unwrap(),
+            Status::Custom("Review".into())
+        );
+        assert!(" - ".parse::<Status>().is_err());
+    }
+
+    #[test]
+    fn stores_built_ins_by_variant_name() {
+        let json =
+            serde_json::to_string(&[Status::InProgress, Status::Custom("Review".into())]).unwrap();
+        assert_eq!(json, r#"["InProgress","Review"]"#);
+        let back: Vec<Status> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, [Status::InProgress, Status::Custom("Review".into())]);
+    }
+
+    #[test]
+    fn sorts_open_statuses_before_closed_ones() {
+        let mut statuses = vec![
+            Status::Custom("Someday".into()),
+            Status::Cancelled,
+            Status::Done,
+            Status::Waiting,
+            Status::Pending,
+        ];
+        statuses.sort();
+        assert_eq!(
+            statuses,
+            [
+                Status::Pending,
+                Status::Waiting,
+                Status::Done,
+                Status::Cancelled,
+                Status::Custom("Someday".into()),
+            ]
+        );
+    }
+}
@@ -1,65 +1,549 @@
-use crate::todo::Todo;
-use colored::*;
+use crate::{
+    dates::Zone,
+    due::Due,
+    error::{TodoError, TodoResult},
+    ids::IdIndex,
+    priority::Priority,
+    schema,
+    status::Status,
+    todo::Todo,
+};
+use chrono::Utc;
 use std::{
+    cell::Cell,
     collections::HashMap,
-    fs::{self, File},
-    io::Write,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
 };
 use uuid::Uuid;
 
-pub fn load_todos_from_file(file_path: &str) -> HashMap<Uuid, Todo> {
-    match fs::read_to_string(file_path) {
-        Ok(data) => match serde_json::from_str::<HashMap<Uuid, Todo>>(&data) {
-            Ok(todos) => {
-                println!(
-                    "{}",
-                    format!("✅ Todos loaded successfully from {file_path}")
-                        .green()
-                        .bold()
-                );
-                todos
+/// Location of `file_name` in the per-user data directory, i.e.
+/// `$XDG_DATA_HOME/todoscope/` on Linux. Falls back to the current
+/// directory when no data directory can be determined.
+pub fn default_data_path(file_name: &str) -> PathBuf {
+    match dirs::data_dir() {
+        Some(dir) => dir.join("todoscope").join(file_name),
+        None => PathBuf::from(file_name),
+    }
+}
+
+/// Conditions on indexed fields that a backend can use to narrow down
+/// which todos it reads. Every condition that is set must hold.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TodoQuery {
+    pub status: Option<Status>,
+    pub priority: Option<Priority>,
+    /// Due before this day, or before this moment if it has a time.
+    pub due_before: Option<Due>,
+}
+
+impl TodoQuery {
+    pub fn matches(&self, todo: &Todo) -> bool {
+        self.status.as_ref().is_none_or(|s| todo.status == *s)
+            && self.priority.is_none_or(|p| todo.priority == p)
+            && self.due_before.is_none_or(|bound| {
+                todo.due_date
+                    .is_some_and(|due| due.is_before(bound, Zone::current()))
+            })
+    }
+}
+
+/// Persistence backend for todos.
+///
+/// Implementors only have to provide `load` and `transaction`; the single
+/// item helpers are built on top of them but can be overridden when a
+/// backend can do better than a full read-modify-write.
+pub trait TodoStore {
+    /// Returns every todo currently in the store. Fails rather than
+    /// returning an empty list when the stored data cannot be read.
+    fn load(&self) -> TodoResult<HashMap<Uuid, Todo>>;
+
+    /// Runs `f` against the full set of todos and persists the result if it
+    /// returns `true`. Returns whether the changes were committed; a failed
+    /// write leaves the previously stored todos untouched.
+    fn transaction(
+        &mut self,
+        f: &mut dyn FnMut(&mut HashMap<Uuid, Todo>) -> bool,
+    ) -> TodoResult<bool>;
+
+    fn get(&self, id: Uuid) -> TodoResult<Option<Todo>> {
+        Ok(self.load()?.remove(&id))
+    }
+
+    /// The todos matching `query`.
+    fn find(&self, query: &TodoQuery) -> TodoResult<HashMap<Uuid, Todo>> {
+        let mut todos = self.load()?;
+        todos.retain(|_, todo| query.matches(todo));
+        Ok(todos)
+    }
+
+    /// Numbers and short IDs for every todo, see [`IdIndex`].
+    fn id_index(&self) -> TodoResult<IdIndex> {
+        Ok(IdIndex::new(&self.load()?))
+    }
+
+    /// Adds a new todo. Returns `false` if a todo with the same ID exists.
+    fn insert(&mut self, todo: Todo) -> TodoResult<bool> {
+        let mut todo = Some(todo);
+        self.transaction(&mut |todos| match todo.take() {
+            Some(todo) if !todos.contains_key(&todo.id) => {
+                todos.insert(todo.id, todo);
+                true
             }
-            Err(error) => {
-                eprintln!(
-                    "{}",
-                    format!("❌ Failed to parse JSON: {error}").red().bold()
+            _ => false,
+        })
+    }
+
+    /// Replaces an existing todo. Returns `false` if it does not exist.
+    fn update(&mut self, todo: Todo) -> TodoResult<bool> {
+        let mut todo = Some(todo);
+        self.transaction(&mut |todos| match todo.take() {
+            Some(todo) if todos.contains_key(&todo.id) => {
+                todos.insert(todo.id, todo);
+                true
+            }
+            _ => false,
+        })
+    }
+
+    fn delete(&mut self, id: Uuid) -> TodoResult<bool> {
+        self.transaction(&mut |todos| todos.remove(&id).is_some())
+    }
+}
+
+/// How many previous versions of the JSON file are kept.
+pub const BACKUPS: usize = 3;
+
+/// Stores todos as a pretty-printed JSON map keyed by ID.
+///
+/// Reads take a shared lock and transactions an exclusive one on a
+/// `.lock` file next to the data file, so concurrent `todo` processes
+/// never lose each other's writes.
+pub struct JsonFileStore {
+    path: PathBuf,
+    last_seen: Cell<Option<u64>>,
+}
+
+impl JsonFileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            last_seen: Cell::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Where the `n`th most recent previous version of the file is kept,
+    /// counting from 1. Each save shifts the backups up by one and drops
+    /// the one past [`BACKUPS`].
+    pub fn backup_path(&self, n: usize) -> PathBuf {
+        self.sibling_with_suffix(&format!(".bak.{n}"))
+    }
+
+    /// Returns `true` if the file on disk no longer matches what this store
+    /// last read or wrote, i.e. another process modified it in between.
+    pub fn changed_on_disk(&self) -> bool {
+        let current = fs::read(&self.path).ok().map(|data| content_hash(&data));
+        self.last_seen.get().is_some() && current != self.last_seen.get()
+    }
+
+    fn lock(&self, exclusive: bool) -> io::Result<File> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.sibling_with_suffix(".lock"))?;
+        if exclusive {
+            file.lock()?;
+        } else {
+            file.lock_shared()?;
+        }
+        Ok(file)
+    }
+
+    fn sibling_with_suffix(&self, suffix: &str) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+
+    fn read(&self) -> TodoResult<HashMap<Uuid, Todo>> {
+        let file_path = self.path.display();
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.last_seen.set(None);
+                log::info!(
+                    "📄 {} not found, starting with an empty list of todos",
+                    self.path.display()
                 );
-                HashMap::new()
+                return Ok(HashMap::new());
             }
-        },
-        Err(_) => {
-            println!(
-                "{}",
-                "⚠️ File not found, starting with an empty list of todos."
-                    .yellow()
-                    .bold()
+            Err(error) => return Err(error.into()),
+        };
+        self.last_seen.set(Some(content_hash(data.as_bytes())));
+
+        let todos = schema::from_json(&data).map_err(|reason| TodoError::Parse {
+            path: self.path.clone(),
+            reason,
+        })?;
+        log::debug!("✅ Loaded {} todo(s) from {file_path}", todos.len());
+        Ok(todos)
+    }
+
+    /// Rebuilds a damaged file from every todo that can still be parsed.
+    /// The damaged file is kept next to it as `<name>.corrupt-<timestamp>`
+    /// and the `.bak.<n>` copies of earlier versions are left untouched.
+    /// Returns `None` if the file is readable and needs no repair.
+    pub fn repair(&mut self) -> TodoResult<Option<RepairReport>> {
+        let _lock = self.lock(true)?;
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        if schema::from_json(&data).is_ok() {
+            return Ok(None);
+        }
+
+        let todos: HashMap<Uuid, Todo> = schema::salvage(&data)
+            .into_iter()
+            .map(|todo| (todo.id, todo))
+            .collect();
+        let original =
+            self.sibling_with_suffix(&format!(".corrupt-{}", Utc::now().format("%Y%m%d%H%M%S")));
+        fs::copy(&self.path, &original)?;
+        self.write(&todos, false)?;
+
+        Ok(Some(RepairReport {
+            salvaged: todos.len(),
+            original,
+        }))
+    }
+
+    fn save(&self, todos: &HashMap<Uuid, Todo>) -> io::Result<()> {
+        self.write(todos, true)
+    }
+
+    /// Writes to a temporary file next to the target, syncs it and renames
+    /// it into place so readers only ever see a complete file.
+    fn write(&self, todos: &HashMap<Uuid, Todo>, backup: bool) -> io::Result<()> {
+        let json = schema::to_json(todos)?;
+        let tmp_path = self.sibling_with_suffix(&format!(".{}.tmp", process::id()));
+
+        let result = write_synced(&tmp_path, json.as_bytes()).and_then(|()| {
+            if backup && self.path.exists() {
+                self.rotate_backups()?;
+            }
+            fs::rename(&tmp_path, &self.path)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result?;
+        sync_parent_dir(&self.path)?;
+        self.last_seen.set(Some(content_hash(json.as_bytes())));
+
+        log::info!("💾 Todos saved to {}", self.path.display());
+        Ok(())
+    }
+
+    /// Shifts `.bak.1`… up by one and copies the current file to `.bak.1`.
+    /// The renames are made durable with the data file's own rename.
+    fn rotate_backups(&self) -> io::Result<()> {
+        for n in (1..BACKUPS).rev() {
+            match fs::rename(self.backup_path(n), self.backup_path(n + 1)) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                _ => {}
+            }
+        }
+        let newest = self.backup_path(1);
+        fs::copy(&self.path, &newest)?;
+        File::open(&newest)?.sync_all()
+    }
+}
+
+/// Outcome of [`JsonFileStore::repair`].
+#[derive(Debug)]
+pub struct RepairReport {
+    pub salvaged: usize,
+    /// Where the damaged file was preserved.
+    pub original: PathBuf,
+}
+
+fn content_hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn write_synced(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl TodoStore for JsonFileStore {
+    fn load(&self) -> TodoResult<HashMap<Uuid, Todo>> {
+        // Reading still works without a lock, e.g. from a read-only directory.
+        let _lock = self.lock(false).ok();
+        self.read()
+    }
+
+    fn transaction(
+        &mut self,
+        f: &mut dyn FnMut(&mut HashMap<Uuid, Todo>) -> bool,
+    ) -> TodoResult<bool> {
+        let _lock = self.lock(true)?;
+        if self.changed_on_disk() {
+            log::warn!(
+                "🔄 {} was modified by another process, using its latest contents",
+                self.path.display()
             );
-            HashMap::new()
         }
+        let mut todos = self.read()?;
+        if !f(&mut todos) {
+            return Ok(false);
+        }
+        self.save(&todos)?;
+        Ok(true)
     }
 }
 
-pub fn save_todos_to_file(todos: &HashMap<Uuid, Todo>, file_path: &str) {
-    match File::create(file_path) {
-        Ok(mut file) => {
-            let json = serde_json::to_string_pretty(todos).unwrap();
-            if let Err(error) = file.write_all(json.as_bytes()) {
-                eprintln!(
-                    "{}",
-                    format!("❌ Failed to write to file: {error}").red().bold()
-                );
-            } else {
-                println!(
-                    "{}",
-                    format!("💾 Todos saved successfully to {file_path}")
-                        .green()
-                        .bold()
-                );
-            }
+/// Keeps todos in memory only. Useful for tests and embedding.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore {
+    todos: HashMap<Uuid, Todo>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl From<HashMap<Uuid, Todo>> for MemoryStore {
+    fn from(todos: HashMap<Uuid, Todo>) -> Self {
+        Self { todos }
+    }
+}
+
+impl TodoStore for MemoryStore {
+    fn load(&self) -> TodoResult<HashMap<Uuid, Todo>> {
+        Ok(self.todos.clone())
+    }
+
+    fn transaction(
+        &mut self,
+        f: &mut dyn FnMut(&mut HashMap<Uuid, Todo>) -> bool,
+    ) -> TodoResult<bool> {
+        let mut todos = self.todos.clone();
+        if !f(&mut todos) {
+            return Ok(false);
         }
-        Err(error) => eprintln!(
-            "{}",
-            format!("❌ Failed to create file: {error}").red().bold()
-        ),
+        self.todos = todos;
+        Ok(true)
+    }
+
+    fn get(&self, id: Uuid) -> TodoResult<Option<Todo>> {
+        Ok(self.todos.get(&id).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::AddTodoInput;
+
+    fn sample_todo(title: &str) -> Todo {
+        Todo::new(AddTodoInput::new(title))
+    }
+
+    #[test]
+    fn memory_store_insert_update_delete() {
+        let mut store = MemoryStore::new();
+        let mut todo = sample_todo("Write tests");
+        let id = todo.id;
+
+        assert!(store.insert(todo.clone()).unwrap());
+        assert!(!store.insert(todo.clone()).unwrap());
+
+        todo.title = "Write more tests".into();
+        assert!(store.update(todo).unwrap());
+        assert_eq!(store.get(id).unwrap().unwrap().title, "Write more tests");
+
+        assert!(store.delete(id).unwrap());
+        assert!(!store.delete(id).unwrap());
+        assert!(store.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejected_transaction_is_not_committed() {
+        let mut store = MemoryStore::new();
+        let todo = sample_todo("Keep me");
+        let id = todo.id;
+        store.insert(todo).unwrap();
+
+        let committed = store.transaction(&mut |todos| {
+            todos.clear();
+            false
+        });
+
+        assert!(!committed.unwrap());
+        assert!(store.get(id).unwrap().is_some());
+    }
+
+    fn temp_json_path() -> PathBuf {
+        std::env::temp_dir().join(format!("todoscope-{}.json", Uuid::new_v4()))
+    }
+
+    #[test]
+    fn json_store_round_trips() {
+        let path = temp_json_path();
+        let mut store = JsonFileStore::new(&path);
+        let todo = sample_todo("Persist me");
+        let id = todo.id;
+
+        assert!(store.insert(todo).unwrap());
+        let reopened = JsonFileStore::new(&path);
+        assert_eq!(reopened.get(id).unwrap().unwrap().title, "Persist me");
+
+        fs::remove_file(store.sibling_with_suffix(".lock")).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn json_store_keeps_rotating_backups() {
+        let path = temp_json_path();
+        let mut store = JsonFileStore::new(&path);
+        let todos: Vec<Todo> = (0..BACKUPS + 2)
+            .map(|i| sample_todo(&format!("Todo {i}")))
+            .collect();
+        for todo in &todos {
+            store.insert(todo.clone()).unwrap();
+        }
+
+        // Backup n holds the file as it was n saves ago.
+        for n in 1..=BACKUPS {
+            let backup = JsonFileStore::new(store.backup_path(n)).load().unwrap();
+            assert_eq!(backup.len(), todos.len() - n);
+        }
+        assert!(!store.backup_path(BACKUPS + 1).exists());
+
+        for n in 1..=BACKUPS {
+            fs::remove_file(store.backup_path(n)).unwrap();
+        }
+        fs::remove_file(store.sibling_with_suffix(".lock")).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn json_store_serializes_concurrent_writers() {
+        let path = temp_json_path();
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut store = JsonFileStore::new(path);
+                    store.insert(sample_todo(&format!("Writer {i}"))).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let store = JsonFileStore::new(&path);
+        assert_eq!(store.load().unwrap().len(), 8);
+
+        for suffix in ["", ".bak.1", ".bak.2", ".bak.3", ".lock"] {
+            let _ = fs::remove_file(store.sibling_with_suffix(suffix));
+        }
+    }
+
+    #[test]
+    fn json_store_detects_external_changes() {
+        let path = temp_json_path();
+        let mut mine = JsonFileStore::new(&path);
+        let mut theirs = JsonFileStore::new(&path);
+        mine.insert(sample_todo("Mine")).unwrap();
+        assert!(!mine.changed_on_disk());
+
+        theirs.insert(sample_todo("Theirs")).unwrap();
+        assert!(mine.changed_on_disk());
+
+        mine.insert(sample_todo("Mine again")).unwrap();
+        assert_eq!(mine.load().unwrap().len(), 3);
+
+        for suffix in ["", ".bak.1", ".bak.2", ".bak.3", ".lock"] {
+            let _ = fs::remove_file(mine.sibling_with_suffix(suffix));
+        }
+    }
+
+    #[test]
+    fn json_store_refuses_to_overwrite_unreadable_files() {
+        let path = temp_json_path();
+        fs::write(&path, "{ not json").unwrap();
+        let mut store = JsonFileStore::new(&path);
+
+        assert!(store.load().is_err());
+        assert!(store.insert(sample_todo("Clobber")).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{ not json");
+
+        let _ = fs::remove_file(store.sibling_with_suffix(".lock"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn repair_salvages_readable_entries_and_keeps_original() {
+        let path = temp_json_path();
+        let mut store = JsonFileStore::new(&path);
+        let kept = sample_todo("Survivor");
+        store.insert(kept.clone()).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        let damaged = json.trim_end().strip_suffix('}').unwrap().to_string();
+        fs::write(&path, &damaged).unwrap();
+
+        let report = store.repair().unwrap().unwrap();
+
+        assert_eq!(report.salvaged, 1);
+        assert_eq!(fs::read_to_string(&report.original).unwrap(), damaged);
+        assert_eq!(store.load().unwrap(), HashMap::from([(kept.id, kept)]));
+        assert!(store.repair().unwrap().is_none());
+
+        fs::remove_file(report.original).unwrap();
+        fs::remove_file(store.sibling_with_suffix(".lock")).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn json_store_reports_failed_writes() {
+        let path = temp_json_path().join("missing-dir").join("todos.json");
+        let mut store = JsonFileStore::new(&path);
+
+        assert!(store.insert(sample_todo("Nowhere to go")).is_err());
+        assert!(!path.exists());
     }
 }
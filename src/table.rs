@@ -0,0 +1,296 @@
+use crate::{config::Config, dates::Zone, ids::IdIndex, todo::Todo};
+use clap::ValueEnum;
+use colored::*;
+use std::io::{self, IsTerminal, Write};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const GAP: &str = "  ";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    Number,
+    Id,
+    Title,
+    Description,
+    Priority,
+    Status,
+    Created,
+    Updated,
+    Started,
+    Completed,
+    Due,
+    Scheduled,
+    Wait,
+    Overdue,
+    Tags,
+    Parent,
+    Subtasks,
+    Recurrence,
+}
+
+pub const DEFAULT_COLUMNS: &[Column] = &[
+    Column::Number,
+    Column::Id,
+    Column::Title,
+    Column::Priority,
+    Column::Status,
+    Column::Due,
+    Column::Tags,
+];
+
+impl Column {
+    fn header(self) -> &'static str {
+        match self {
+            Column::Number => "#",
+            Column::Id => "ID",
+            Column::Title => "Title",
+            Column::Description => "Description",
+            Column::Priority => "Priority",
+            Column::Status => "Status",
+            Column::Created => "Created",
+            Column::Updated => "Updated",
+            Column::Started => "Started",
+            Column::Completed => "Completed",
+            Column::Due => "Due",
+            Column::Scheduled => "Scheduled",
+            Column::Wait => "Wait",
+            Column::Overdue => "Overdue",
+            Column::Tags => "Tags",
+            Column::Parent => "Parent",
+            Column::Subtasks => "Subtasks",
+            Column::Recurrence => "Recurrence",
+        }
+    }
+
+    /// Free-text columns give up width first when the table is too wide.
+    fn shrinkable(self) -> bool {
+        matches!(
+            self,
+            Column::Title | Column::Description | Column::Tags | Column::Subtasks
+        )
+    }
+
+    fn cell(self, todo: &Todo, ids: &IdIndex) -> String {
+        let date_format = &Config::current().date_format;
+        let zone = Zone::current();
+        let local = |at| zone.local(at).format(date_format).to_string();
+        match self {
+            Column::Number => ids
+                .number(todo.id)
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            Column::Id => ids.short_id(todo.id),
+            Column::Title => todo.title.clone(),
+            Column::Description => todo.description.clone().unwrap_or_default(),
+            Column::Priority => todo.priority.to_string(),
+            Column::Status => todo.status.to_string(),
+            Column::Created => local(todo.created_at),
+            Column::Updated => local(todo.updated_at),
+            Column::Started => todo.started_at.map(local).unwrap_or_default(),
+            Column::Completed => todo.completed_at.map(local).unwrap_or_default(),
+            Column::Due => todo
+                .due_date
+                .map(|due| due.format(date_format, zone))
+                .unwrap_or_default(),
+            Column::Scheduled => todo
+                .scheduled
+                .map(|scheduled| scheduled.format(date_format, zone))
+                .unwrap_or_default(),
+            Column::Wait => todo
+                .wait_until
+                .map(|wait| wait.format(date_format, zone))
+                .unwrap_or_default(),
+            Column::Overdue => if todo.is_overdue() { "yes" } else { "" }.to_string(),
+            Column::Tags => todo.tags.as_deref().unwrap_or_default().join(", "),
+            Column::Parent => todo
+                .parent_id
+                .map(|id| ids.short_id(id))
+                .unwrap_or_default(),
+            Column::Subtasks => todo
+                .subtasks
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .map(|&id| ids.short_id(id))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Column::Recurrence => todo
+                .recurrence
+                .as_ref()
+                .map(|r| r.to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Colors a cell the same way the block view does.
+    fn paint(self, todo: &Todo, text: String) -> ColoredString {
+        match self {
+            Column::Number => text.dimmed(),
+            Column::Id => text.cyan(),
+            Column::Title => text.bold(),
+            Column::Priority => todo.priority.paint(&text),
+            Column::Status => todo.status.paint(&text),
+            Column::Due | Column::Overdue if todo.is_overdue() => text.red(),
+            Column::Due => text.yellow(),
+            Column::Wait if todo.is_deferred() => text.dimmed(),
+            Column::Tags => text.cyan(),
+            _ => text.normal(),
+        }
+    }
+}
+
+/// Width of the terminal stdout is attached to, or `None` when output is
+/// piped, in which case nothing is truncated.
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(width, _)| width.0 as usize)
+}
+
+/// Writes one row per todo. Columns are as wide as their widest cell; if
+/// that exceeds `max_width`, free-text columns are truncated with `…`.
+pub fn write_table(
+    out: &mut dyn Write,
+    todos: &[&Todo],
+    ids: &IdIndex,
+    columns: &[Column],
+    max_width: Option<usize>,
+) -> io::Result<()> {
+    let columns = if columns.is_empty() {
+        DEFAULT_COLUMNS
+    } else {
+        columns
+    };
+    let rows: Vec<Vec<String>> = todos
+        .iter()
+        .map(|todo| {
+            columns
+                .iter()
+                .map(|column| column.cell(todo, ids))
+                .collect()
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.header().width()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+    if let Some(max_width) = max_width {
+        fit_widths(&mut widths, columns, max_width);
+    }
+
+    let header: Vec<String> = columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, &width)| pad(column.header(), width))
+        .collect();
+    writeln!(out, "{}", header.join(GAP).trim_end().bold().underline())?;
+
+    for (todo, row) in todos.iter().zip(&rows) {
+        let cells: Vec<String> = columns
+            .iter()
+            .zip(&widths)
+            .zip(row)
+            .map(|((column, &width), cell)| {
+                let text = pad(cell, width);
+                column.paint(todo, text.trim_end().to_string()).to_string()
+                    + &" ".repeat(text.len() - text.trim_end().len())
+            })
+            .collect();
+        writeln!(out, "{}", cells.join(GAP).trim_end())?;
+    }
+    Ok(())
+}
+
+/// Narrows the widest shrinkable column one cell at a time until the row
+/// fits, keeping at least the header (or five cells) of each column.
+fn fit_widths(widths: &mut [usize], columns: &[Column], max_width: usize) {
+    let gaps = GAP.len() * widths.len().saturating_sub(1);
+    let mut overflow = (widths.iter().sum::<usize>() + gaps).saturating_sub(max_width);
+
+    while overflow > 0 {
+        let widest = (0..widths.len())
+            .filter(|&i| columns[i].shrinkable() && widths[i] > columns[i].header().width().max(5))
+            .max_by_key(|&i| widths[i]);
+        let Some(i) = widest else { break };
+        widths[i] -= 1;
+        overflow -= 1;
+    }
+}
+
+/// Truncates `text` to `width` display cells (ending in `…` if cut) and
+/// pads it with spaces to exactly that width.
+fn pad(text: &str, width: usize) -> String {
+    let mut result = String::new();
+    if text.width() > width {
+        let mut used = 0;
+        for ch in text.chars() {
+            let w = ch.width().unwrap_or(0);
+            if used + w + 1 > width {
+                break;
+            }
+            result.push(ch);
+            used += w;
+        }
+        result.push('…');
+    } else {
+        result.push_str(text);
+    }
+    let padding = width.saturating_sub(result.width());
+    result.extend(std::iter::repeat_n(' ', padding));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input::AddTodoInput, priority::Priority};
+
+    fn todo(title: &str) -> Todo {
+        Todo::new(AddTodoInput {
+            priority: Priority::High,
+            tags: Some(vec!["work".into()]),
+            ..AddTodoInput::new(title)
+        })
+    }
+
+    fn render(todos: &[&Todo], columns: &[Column], max_width: Option<usize>) -> Vec<String> {
+        colored::control::set_override(false);
+        let mut out = Vec::new();
+        let all = todos.iter().map(|&todo| (todo.id, todo.clone())).collect();
+        write_table(&mut out, todos, &IdIndex::new(&all), columns, max_width).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn renders_selected_columns_aligned() {
+        let (a, b) = (todo("Short"), todo("A much longer title"));
+        let lines = render(&[&a, &b], &[Column::Title, Column::Priority], None);
+        assert_eq!(lines[0], "Title                Priority");
+        assert_eq!(lines[1], "Short                High");
+        assert_eq!(lines[2], "A much longer title  High");
+    }
+
+    #[test]
+    fn truncates_titles_to_fit_width() {
+        let long = todo("An extremely long title that will not fit");
+        let lines = render(&[&long], &[Column::Title, Column::Tags], Some(20));
+        assert!(lines.iter().all(|line| line.width() <= 20));
+        assert!(lines[1].starts_with("An extremely"));
+        assert!(lines[1].contains('…'));
+        assert!(lines[1].ends_with("work"));
+    }
+
+    #[test]
+    fn pads_by_display_width() {
+        assert_eq!(pad("日本", 6), "日本  ");
+        assert_eq!(pad("日本語テキスト", 6), "日本… ");
+    }
+}
@@ -1,11 +1,20 @@
-use std::fmt::{Display, Formatter, Result};
-
+use crate::config::Config;
+use crate::dates::{Zone, parse_due};
+use crate::due::Due;
+use crate::filter::Filter;
+use crate::fulltext::{SearchIndex, TextQuery};
+use crate::ids::IdIndex;
 use crate::input::AddTodoInput;
+use crate::output::{OutputFormat, OutputOptions, print_todos};
 use crate::status::Status;
-use crate::storage::{load_todos_from_file, save_todos_to_file};
+use crate::storage::TodoStore;
+use crate::todo_cli::save_edit;
 use crate::utils::{read_input, read_optional_input};
-use crate::{priority::Priority, recurrence::Recurrence};
-use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use crate::{
+    priority::Priority,
+    recurrence::{Anchor, Frequency, Recurrence},
+};
+use chrono::{DateTime, Utc};
 use colored::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -17,124 +26,108 @@ pub struct Todo {
     pub description: Option<String>,
     pub priority: Priority,
     pub status: Status,
-    pub created_at: NaiveDateTime,
-    pub due_date: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// When any field of the todo last changed.
+    pub updated_at: DateTime<Utc>,
+    pub due_date: Option<Due>,
+    /// The day the todo is planned to be worked on.
+    #[serde(default)]
+    pub scheduled: Option<Due>,
+    /// Until when the todo is deferred: `list` leaves it out before then.
+    #[serde(default)]
+    pub wait_until: Option<Due>,
     pub tags: Option<Vec<String>>,
     pub parent_id: Option<Uuid>,
     pub subtasks: Option<Vec<Uuid>>,
     pub recurrence: Option<Recurrence>,
-}
-
-impl Display for Todo {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        let priority_str = self.priority.to_string();
-        let priority_color = match priority_str.as_str() {
-            "High" => priority_str.red().bold(),
-            "Medium" => priority_str.yellow(),
-            "Low" => priority_str.green(),
-            _ => priority_str.normal(),
-        };
-        let status_str = self.status.to_string();
-        let status_color = match status_str.as_str() {
-            "Pending" => status_str.red().bold(),
-            "In Progress" => status_str.yellow(),
-            "Done" => status_str.green(),
-            _ => status_str.normal(),
-        };
-        let recurrence_str = match &self.recurrence {
-            Some(r) => r.to_string(),
-            None => "None".to_string(),
-        };
-        let due_date_str = match &self.due_date {
-            Some(d) => d.to_rfc3339(),
-            None => "None".to_string(),
-        };
-        let tags_str = match &self.tags {
-            Some(tags) => tags.join(", "),
-            None => "None".to_string(),
-        };
-        let subtasks_str = match &self.subtasks {
-            Some(subs) if !subs.is_empty() => subs
-                .iter()
-                .map(|id| id.to_string())
-                .collect::<Vec<_>>()
-                .join(", "),
-            _ => "None".to_string(),
-        };
-
-        let todo_block = format!(
-            "{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n{} {}\n",
-            "ID:".bold(),
-            self.id.to_string().cyan(),
-            "Title:".bold(),
-            self.title.bold(),
-            "Description:".bold(),
-            self.description.as_deref().unwrap_or("None"),
-            "Priority:".bold(),
-            priority_color,
-            "Status:".bold(),
-            status_color,
-            "Due Date:".bold(),
-            due_date_str,
-            "Tags:".bold(),
-            tags_str,
-            "Parent ID:".bold(),
-            self.parent_id
-                .map(|id| id.to_string())
-                .unwrap_or("None".to_string()),
-            "Subtasks:".bold(),
-            subtasks_str,
-            "Recurrence:".bold(),
-            recurrence_str.cyan(),
-        );
-
-        if self.is_overdue() {
-            write!(
-                f,
-                "{}\n{}",
-                "⚠️ OVERDUE!".red().bold(),
-                todo_block.red().bold()
-            )
-        } else {
-            f.write_str(&todo_block)
-        }
-    }
+    /// ID of the first todo in this one's recurring series. Set once an
+    /// occurrence has been completed and the next one created.
+    #[serde(default)]
+    pub series_id: Option<Uuid>,
+    /// When the todo first moved to In Progress.
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the todo was last marked Done or Cancelled.
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
 }
 
 impl Todo {
     pub fn new(input: AddTodoInput) -> Self {
-        Self {
+        let now = Utc::now();
+        let mut todo = Self {
             id: Uuid::new_v4(),
             title: input.title,
             description: input.description,
             priority: input.priority,
-            status: input.status,
-            created_at: Utc::now().naive_utc(),
+            status: Status::Pending,
+            created_at: now,
+            updated_at: now,
             due_date: input.due_date,
+            scheduled: input.scheduled,
+            wait_until: input.wait_until,
             tags: input.tags,
             parent_id: input.parent_id,
             subtasks: input.subtasks,
             recurrence: input.recurrence,
-        }
+            series_id: None,
+            started_at: None,
+            completed_at: None,
+        };
+        todo.set_status(input.status);
+        todo.updated_at = now;
+        todo
     }
 
-    pub fn is_overdue(&self) -> bool {
-        self.due_date.map(|due| Utc::now() > due).unwrap_or(false)
+    /// Records that the todo was just modified.
+    pub fn touch(&mut self) {
+        self.updated_at = Utc::now();
     }
-}
 
-pub fn add_todo(file_path: &str) {
-    let mut todos = load_todos_from_file(file_path);
+    /// Changes the status and records when work started or finished.
+    /// `started_at` keeps the first start; `completed_at` is set when the
+    /// todo is closed (see [`Status::is_closed`]) and cleared when it is
+    /// reopened.
+    /// Returns `false` if the todo already had this status.
+    pub fn set_status(&mut self, status: Status) -> bool {
+        if self.status == status {
+            return false;
+        }
+        let now = Utc::now();
+        if status.is_closed() {
+            self.completed_at = Some(now);
+        } else {
+            self.completed_at = None;
+            if status == Status::InProgress {
+                self.started_at.get_or_insert(now);
+            }
+        }
+        self.status = status;
+        self.updated_at = now;
+        true
+    }
 
-    let id = Uuid::new_v4();
+    pub fn is_overdue(&self) -> bool {
+        self.due_date
+            .is_some_and(|due| due.is_overdue_at(Utc::now(), Zone::current()))
+    }
 
-    if todos.contains_key(&id) {
-        println!(
-            "{}",
-            "❌ A todo with this ID already exists. Try again.".red()
-        );
-        return;
+    /// Whether the todo waits until a time that has not come yet.
+    pub fn is_deferred(&self) -> bool {
+        self.wait_until
+            .is_some_and(|wait| wait.is_future_at(Utc::now(), Zone::current()))
     }
+}
+
+pub fn add_todo(store: &mut dyn TodoStore) {
+    let todos = match store.load() {
+        Ok(todos) => todos,
+        Err(error) => {
+            println!("{}", format!("❌ Failed to load todos: {error}").red());
+            return;
+        }
+    };
+    let ids = IdIndex::new(&todos);
 
     println!("{}", "Enter title:".blue().bold());
 
@@ -150,11 +143,18 @@ pub fn add_todo(file_path: &str) {
 
     let due_date = read_optional_due_date();
 
+    let scheduled = read_optional_date("scheduled date");
+
+    let wait_until = read_optional_date("wait-until date");
+
     let tags = read_optional_tags();
 
-    let parent_id = read_optional_uuid("Enter parent task ID (optional):");
+    let parent_id = read_optional_id(&ids, "Enter parent task ID or number (optional):");
 
-    let subtasks = read_optional_uuids("Enter subtasks IDs separated by commas (optional):");
+    let subtasks = read_optional_ids(
+        &ids,
+        "Enter subtasks IDs or numbers separated by commas (optional):",
+    );
 
     let recurrence = read_recurrence(None);
 
@@ -164,21 +164,33 @@ pub fn add_todo(file_path: &str) {
         priority,
         status,
         due_date,
+        scheduled,
+        wait_until,
         tags,
         parent_id,
         subtasks,
         recurrence,
     });
-    todos.insert(todo.id, todo);
 
-    save_todos_to_file(&todos, file_path);
-
-    println!("{}", "✅ Todo added successfully.".green().bold());
+    match store.insert(todo) {
+        Ok(true) => println!("{}", "✅ Todo added successfully.".green().bold()),
+        Ok(false) => println!(
+            "{}",
+            "❌ A todo with this ID already exists. Try again.".red()
+        ),
+        Err(error) => println!("{}", format!("❌ Failed to save todo: {error}").red()),
+    }
 }
 
-pub fn retrieve_todos_sorted(file_path: &str) {
+pub fn retrieve_todos_sorted(store: &dyn TodoStore) {
     loop {
-        let todos = load_todos_from_file(file_path);
+        let todos = match store.load() {
+            Ok(todos) => todos,
+            Err(error) => {
+                println!("{}", format!("❌ Failed to load todos: {error}").red());
+                return;
+            }
+        };
 
         if todos.is_empty() {
             println!("{}", "❌ No todos found.".red().bold());
@@ -191,7 +203,8 @@ pub fn retrieve_todos_sorted(file_path: &str) {
         println!("{}", "3. Creation order".magenta());
         println!("{}", "4. Due date".cyan());
         println!("{}", "5. Overdue tasks first".red());
-        println!("{}", "6. Back to main menu".red());
+        println!("{}", "6. Recently updated first".cyan());
+        println!("{}", "7. Back to main menu".red());
 
         let choice = read_input::<u32>();
 
@@ -202,7 +215,7 @@ pub fn retrieve_todos_sorted(file_path: &str) {
                 todo_list.sort_by_key(|t| t.priority);
             }
             2 => {
-                todo_list.sort_by_key(|t| t.status);
+                todo_list.sort_by(|a, b| a.status.cmp(&b.status));
             }
             3 => {
                 todo_list.sort_by_key(|t| t.created_at);
@@ -213,7 +226,10 @@ pub fn retrieve_todos_sorted(file_path: &str) {
             5 => {
                 todo_list.sort_by_key(|t| !t.is_overdue());
             }
-            6 => break,
+            6 => {
+                todo_list.sort_by_key(|t| std::cmp::Reverse(t.updated_at));
+            }
+            7 => break,
             _ => {
                 println!(
                     "{}",
@@ -225,17 +241,28 @@ pub fn retrieve_todos_sorted(file_path: &str) {
         }
 
         println!("{}", "--- Todos ---".bold().blue());
-        for todo in &todo_list {
-            println!("{todo}");
+        let table = OutputOptions {
+            format: OutputFormat::Table,
+            ..Default::default()
+        };
+        if let Err(error) = print_todos(&todo_list, &IdIndex::new(&todos), &table) {
+            println!("{}", format!("❌ Failed to print todos: {error}").red());
         }
+        println!();
     }
 }
 
-pub fn search_todos<F>(file_path: &str, predicate: F)
+pub fn search_todos<F>(store: &dyn TodoStore, predicate: F)
 where
     F: Fn(&Todo) -> bool,
 {
-    let todos = load_todos_from_file(file_path);
+    let todos = match store.load() {
+        Ok(todos) => todos,
+        Err(error) => {
+            println!("{}", format!("❌ Failed to load todos: {error}").red());
+            return;
+        }
+    };
 
     if todos.is_empty() {
         println!("{}", "⚠️ No todos found. The file is empty.".yellow());
@@ -251,126 +278,182 @@ where
             "{}",
             format!("Found {} todo(s):", results.len()).green().bold()
         );
-        for todo in results {
-            println!("{todo}");
-        }
+        print_human(&results, &IdIndex::new(&todos));
+    }
+}
+
+/// Prints `todos` the way `list` and `search` do by default.
+fn print_human(todos: &[&Todo], ids: &IdIndex) {
+    if let Err(error) = print_todos(todos, ids, &OutputOptions::default()) {
+        println!("{}", format!("❌ Failed to print todos: {error}").red());
     }
 }
 
-pub fn search_todo_by_id(file_path: &str, id: Uuid) {
-    search_todos(file_path, move |todo| todo.id == id);
+pub fn search_todo_by_id(store: &dyn TodoStore, id: Uuid) {
+    search_todos(store, move |todo| todo.id == id);
 }
 
-pub fn search_todo_by_title(file_path: &str, query: &str) {
+pub fn search_todo_by_title(store: &dyn TodoStore, query: &str) {
     let query_lower = query.to_lowercase();
-    search_todos(file_path, move |todo| {
+    search_todos(store, move |todo| {
         todo.title.to_lowercase().contains(&query_lower)
     });
 }
 
-pub fn search_todo_by_priority(file_path: &str, priority: Priority) {
-    search_todos(file_path, move |todo| todo.priority == priority);
+/// Full-text search, best match first with the matches highlighted.
+pub fn search_todo_by_text(store: &dyn TodoStore, query: TextQuery) {
+    let todos = match store.load() {
+        Ok(todos) => todos,
+        Err(error) => {
+            println!("{}", format!("❌ Failed to load todos: {error}").red());
+            return;
+        }
+    };
+    let results: Vec<&Todo> = SearchIndex::new(todos.values())
+        .search(&query)
+        .into_iter()
+        .map(|(id, _)| &todos[&id])
+        .collect();
+    if results.is_empty() {
+        println!("{}", "⚠️ No todos found matching the criteria.".yellow());
+        return;
+    }
+    println!(
+        "{}",
+        format!("Found {} todo(s):", results.len()).green().bold()
+    );
+    let options = OutputOptions {
+        highlight: Some(query),
+        ..Default::default()
+    };
+    if let Err(error) = print_todos(&results, &IdIndex::new(&todos), &options) {
+        println!("{}", format!("❌ Failed to print todos: {error}").red());
+    }
+}
+
+pub fn search_todo_by_priority(store: &dyn TodoStore, priority: Priority) {
+    search_todos(store, move |todo| todo.priority == priority);
 }
 
-pub fn search_todo_by_status(file_path: &str, status: Status) {
-    search_todos(file_path, move |todo| todo.status == status);
+pub fn search_todo_by_status(store: &dyn TodoStore, status: Status) {
+    search_todos(store, move |todo| todo.status == status);
 }
 
-pub fn search_menu(file_path: &str) {
+pub fn search_menu(store: &dyn TodoStore) {
     loop {
         println!("{}", "Search by:".blue().bold());
         println!("{}", "1. ID".cyan());
-        println!("{}", "2. Title".magenta());
+        println!("{}", "2. Text (title, description, tags)".magenta());
         println!("{}", "3. Priority".yellow());
         println!("{}", "4. Status".green());
         println!("{}", "5. Due date".cyan());
         println!("{}", "6. Recurrence".magenta());
         println!("{}", "7. Tags".yellow());
         println!("{}", "8. Parent task ID".green());
-        println!("{}", "9. Back to main menu".red());
+        println!("{}", "9. Filter expression".cyan());
+        println!("{}", "10. Back to main menu".red());
 
         let choice = read_input::<u32>();
 
         match choice {
             1 => {
-                println!("{}", "Enter the ID to search:".blue().bold());
+                println!("{}", "Enter the ID or number to search:".blue().bold());
                 let id_input = read_input::<String>();
-                match Uuid::parse_str(&id_input) {
+                match store.id_index().unwrap_or_default().resolve(&id_input) {
                     Ok(id) => {
-                        search_todo_by_id(file_path, id);
+                        search_todo_by_id(store, id);
                     }
-                    Err(_) => println!("{}", "⚠️ Invalid UUID format.".red()),
+                    Err(error) => println!("{}", format!("⚠️ {error}").red()),
                 }
             }
             2 => {
-                println!("{}", "Enter the title of the todo to search:".blue().bold());
-                let title_query = read_input::<String>();
-                search_todo_by_title(file_path, &title_query);
+                println!(
+                    "{}",
+                    "Enter words to search for (\"exact phrase\", prefix*):"
+                        .blue()
+                        .bold()
+                );
+                match read_input::<String>().parse::<TextQuery>() {
+                    Ok(query) => search_todo_by_text(store, query),
+                    Err(error) => println!("{}", format!("⚠️ {error}").yellow()),
+                }
             }
             3 => {
                 let priority = read_priority();
-                search_todo_by_priority(file_path, priority);
+                search_todo_by_priority(store, priority);
             }
             4 => {
                 let status = read_status();
-                search_todo_by_status(file_path, status);
+                search_todo_by_status(store, status);
             }
             5 => {
-                println!(
-                    "{}",
-                    "Enter due date to search (YYYY-MM-DD HH:MM):".blue().bold()
-                );
+                println!("{}", "Enter due date to search:".blue().bold());
                 if let Some(due_date) = read_optional_due_date() {
-                    search_todos(file_path, move |t| {
-                        t.due_date.map(|d| d == due_date).unwrap_or(false)
+                    let zone = Zone::current();
+                    search_todos(store, move |t| {
+                        t.due_date
+                            .is_some_and(|d| d.date(zone) == due_date.date(zone))
                     });
                 }
             }
             6 => {
                 println!(
                     "{}",
-                    "Enter recurrence to search: (daily, weekly, or custom text)"
+                    "Enter recurrence to search: (e.g. daily or every 2 weeks on mon)"
                         .blue()
                         .bold()
                 );
                 if let Some(rec) = read_optional_input::<Recurrence>() {
-                    search_todos(file_path, move |t| t.recurrence.as_ref() == Some(&rec));
+                    search_todos(store, move |t| t.recurrence.as_ref() == Some(&rec));
                 }
             }
             7 => {
                 println!("{}", "Enter tag to search:".blue().bold());
                 if let Some(tag) = read_optional_input::<String>() {
-                    search_todos(file_path, move |t| {
+                    search_todos(store, move |t| {
                         t.tags.as_ref().is_some_and(|tags| tags.contains(&tag))
                     });
                 }
             }
             8 => {
-                println!("{}", "Enter parent task ID to search:".blue().bold());
-                if let Some(pid) = read_optional_input::<Uuid>() {
-                    search_todos(file_path, move |t| t.parent_id == Some(pid));
+                let ids = store.id_index().unwrap_or_default();
+                if let Some(pid) = read_optional_id(&ids, "Enter parent task ID to search:") {
+                    search_todos(store, move |t| t.parent_id == Some(pid));
                 }
             }
-            9 => break,
+            9 => {
+                if let Some(filter) = read_optional_filter() {
+                    search_todos(store, move |t| filter.matches(t));
+                }
+            }
+            10 => break,
             _ => println!("{}", "❌ Invalid choice, try again.".red().bold()),
         }
     }
 }
 
-pub fn update_todo(file_path: &str) {
-    let mut todos = load_todos_from_file(file_path);
-
-    if todos.is_empty() {
-        println!("{}", "⚠️ No todos found. The file is empty.".yellow());
-        return;
-    }
+pub fn update_todo(store: &mut dyn TodoStore) {
+    let todos = match store.load() {
+        Ok(todos) if todos.is_empty() => {
+            println!("{}", "⚠️ No todos found. The file is empty.".yellow());
+            return;
+        }
+        Ok(todos) => todos,
+        Err(error) => {
+            println!("{}", format!("❌ Failed to load todos: {error}").red());
+            return;
+        }
+    };
+    let ids = IdIndex::new(&todos);
 
-    println!("Please enter the id of the todo you would like to update:");
+    println!("Please enter the id or number of the todo you would like to update:");
     let id_input = read_input::<String>();
-    match Uuid::parse_str(&id_input) {
+    match ids.resolve(&id_input) {
         Ok(id) => {
-            if let Some(todo) = todos.get_mut(&id) {
-                println!("Updating todo: \n{todo}");
+            if let Ok(Some(mut todo)) = store.get(id) {
+                let original = todo.clone();
+                println!("Updating todo:");
+                print_human(&[&todo], &ids);
 
                 println!(
                     "{}",
@@ -402,7 +485,15 @@ pub fn update_todo(file_path: &str) {
                 println!("{}", "Do you want to update status? (y/n)".blue());
                 let choice = read_input::<String>();
                 if choice.eq_ignore_ascii_case("y") {
-                    todo.status = read_status();
+                    let status = read_status();
+                    match todo.status.check_transition(&status) {
+                        Ok(()) => {
+                            todo.set_status(status);
+                        }
+                        Err(error) => {
+                            println!("{}", format!("⚠️ {error}, keeping it.").yellow())
+                        }
+                    }
                 }
 
                 println!("{}", "Do you want to update due date? (y/n)".blue());
@@ -410,6 +501,16 @@ pub fn update_todo(file_path: &str) {
                     todo.due_date = read_optional_due_date();
                 }
 
+                println!("{}", "Do you want to update scheduled date? (y/n)".blue());
+                if read_input::<String>().eq_ignore_ascii_case("y") {
+                    todo.scheduled = read_optional_date("scheduled date");
+                }
+
+                println!("{}", "Do you want to update wait-until date? (y/n)".blue());
+                if read_input::<String>().eq_ignore_ascii_case("y") {
+                    todo.wait_until = read_optional_date("wait-until date");
+                }
+
                 println!("{}", "Do you want to update tags? (y/n)".blue());
                 if read_input::<String>().eq_ignore_ascii_case("y") {
                     todo.tags = read_optional_tags();
@@ -417,10 +518,13 @@ pub fn update_todo(file_path: &str) {
 
                 println!("{}", "Do you want to update parent task? (y/n)".blue());
                 if read_input::<String>().eq_ignore_ascii_case("y") {
-                    todo.parent_id = read_optional_uuid(&format!(
-                        "Enter parent task ID (current: {:?}, press Enter to skip):",
-                        todo.parent_id
-                    ));
+                    todo.parent_id = read_optional_id(
+                        &ids,
+                        &format!(
+                            "Enter parent task ID (current: {:?}, press Enter to skip):",
+                            todo.parent_id
+                        ),
+                    );
                 }
 
                 println!("{}", "Do you want to update recurrence? (y/n)".blue());
@@ -430,45 +534,95 @@ pub fn update_todo(file_path: &str) {
 
                 println!("{}", "Do you want to update subtasks? (y/n)".blue());
                 if read_input::<String>().eq_ignore_ascii_case("y") {
-                    todo.subtasks = read_optional_uuids(
+                    todo.subtasks = read_optional_ids(
+                        &ids,
                         "Enter subtask IDs (comma separated, press enter to Skip):",
                     );
                 }
 
-                save_todos_to_file(&todos, file_path);
-                println!("{}", "✅ Todo updated successfully.".green().bold());
+                if todo != original {
+                    todo.touch();
+                }
+
+                // Only save if nobody else touched this todo while we were prompting.
+                let mut edited = Some(todo);
+                let mut conflict = false;
+                let mut rejected = None;
+                let mut next = None;
+                let result = store.transaction(&mut |todos| match todos.get(&id) {
+                    Some(current) if *current == original => {
+                        match edited.take().map(|todo| save_edit(todos, todo)) {
+                            Some(Ok(occurrence)) => {
+                                next = occurrence;
+                                true
+                            }
+                            Some(Err(error)) => {
+                                rejected = Some(error);
+                                false
+                            }
+                            None => false,
+                        }
+                    }
+                    Some(_) => {
+                        conflict = true;
+                        false
+                    }
+                    None => false,
+                });
+                let result = result.and_then(|saved| rejected.map_or(Ok(saved), Err));
+                match result {
+                    Ok(true) => {
+                        println!("{}", "✅ Todo updated successfully.".green().bold());
+                        if let Some(next) = &next {
+                            println!("{}", "🔁 Next occurrence created:".cyan());
+                            // Reloaded so the new todo gets its number.
+                            print_human(&[next], &store.id_index().unwrap_or_default());
+                        }
+                    }
+                    Ok(false) if conflict => println!(
+                        "{}",
+                        "⚠️ This todo was changed by another session, please try again.".yellow()
+                    ),
+                    Ok(false) => println!("{}", format!("❌ No todo found with id: {id}").red()),
+                    Err(error) => {
+                        println!("{}", format!("❌ Failed to save todo: {error}").red())
+                    }
+                }
             } else {
                 println!("{}", format!("❌ No todo found with id: {id}").red());
             }
         }
-        Err(_) => println!("{}", "⚠️ Invalid UUID format.".red()),
+        Err(error) => println!("{}", format!("⚠️ {error}").red()),
     }
 }
 
-pub fn delete_todo(file_path: &str) {
-    let mut todos = load_todos_from_file(file_path);
-
-    if todos.is_empty() {
-        println!("{}", "⚠️ No todos found. The file is empty.".yellow());
-        return;
-    }
+pub fn delete_todo(store: &mut dyn TodoStore) {
+    let todos = match store.load() {
+        Ok(todos) if todos.is_empty() => {
+            println!("{}", "⚠️ No todos found. The file is empty.".yellow());
+            return;
+        }
+        Ok(todos) => todos,
+        Err(error) => {
+            println!("{}", format!("❌ Failed to load todos: {error}").red());
+            return;
+        }
+    };
+    let ids = IdIndex::new(&todos);
     println!(
         "{}",
-        "Please enter the id of the todo you would like to delete:"
+        "Please enter the id or number of the todo you would like to delete:"
             .blue()
             .bold()
     );
     let id_input = read_input::<String>();
-    match Uuid::parse_str(&id_input) {
-        Ok(id) => {
-            if todos.remove(&id).is_some() {
-                save_todos_to_file(&todos, file_path);
-                println!("{}", "✅ Todo deleted successfully".green().bold());
-            } else {
-                println!("{}", format!("❌ No todo found with id: {id}").red());
-            }
-        }
-        Err(_) => println!("{}", "⚠️ Invalid UUID format.".red()),
+    match ids.resolve(&id_input) {
+        Ok(id) => match store.delete(id) {
+            Ok(true) => println!("{}", "✅ Todo deleted successfully".green().bold()),
+            Ok(false) => println!("{}", format!("❌ No todo found with id: {id}").red()),
+            Err(error) => println!("{}", format!("❌ Failed to save todos: {error}").red()),
+        },
+        Err(error) => println!("{}", format!("⚠️ {error}").red()),
     }
 }
 
@@ -494,19 +648,18 @@ fn read_priority() -> Priority {
 }
 
 fn read_status() -> Status {
+    let statuses = Config::current().all_statuses();
     loop {
         println!("{}", "Choose status:".blue().bold());
-        println!("{}", "1. Pending".red().bold());
-        println!("{}", "2. In Progress".yellow());
-        println!("{}", "3. Done".green());
+        for (i, status) in statuses.iter().enumerate() {
+            println!("{}", status.paint(&format!("{}. {status}", i + 1)));
+        }
 
-        let choice = read_input::<u32>();
+        let choice = read_input::<usize>();
 
-        match choice {
-            1 => return Status::Pending,
-            2 => return Status::InProgress,
-            3 => return Status::Done,
-            _ => {
+        match choice.checked_sub(1).and_then(|i| statuses.get(i)) {
+            Some(status) => return status.clone(),
+            None => {
                 println!("{}", "❌ Invalid choice, try again.".red().bold());
                 continue;
             }
@@ -524,57 +677,92 @@ pub fn read_recurrence(current: Option<&Recurrence>) -> Option<Recurrence> {
         println!("{}", prompt.blue().bold());
         println!("{}", "1. Daily".green());
         println!("{}", "2. Weekly".yellow());
-        println!("{}", "3. Custom".magenta());
+        println!("{}", "3. Monthly".cyan());
+        println!("{}", "4. Other rule".magenta());
+        println!("{}", "5. No recurrence".dimmed());
 
         if let Some(choice) = read_optional_input::<u32>() {
-            match choice {
-                1 => return Some(Recurrence::Daily),
-                2 => return Some(Recurrence::Weekly),
-                3 => {
-                    println!("{}", "Enter custom recurrence description".blue());
-                    if let Some(custom) = read_optional_input::<String>() {
-                        return Some(Recurrence::Custom(custom));
-                    } else {
-                        return None;
+            let frequency = match choice {
+                1 => Frequency::Daily,
+                2 => Frequency::Weekly,
+                3 => Frequency::Monthly,
+                4 => {
+                    println!(
+                        "{}",
+                        "Enter a rule, e.g. \"every 2 weeks on mon,thu\" or an RRULE:".blue()
+                    );
+                    match read_optional_input::<String>().map(|rule| rule.parse()) {
+                        Some(Ok(rule)) => return Some(rule),
+                        Some(Err(error)) => {
+                            println!("{}", format!("❌ {error}, try again.").red().bold());
+                            continue;
+                        }
+                        None => return current.cloned(),
                     }
                 }
+                5 => return None,
                 _ => {
                     println!("{}", "❌ Invalid choice, try again.".red().bold());
                     continue;
                 }
+            };
+            let mut rule = Recurrence::every(1, frequency);
+            println!(
+                "{}",
+                "Repeat from when it is done instead of the due date? (y/n)".blue()
+            );
+            if read_input::<String>().eq_ignore_ascii_case("y") {
+                rule.anchor = Anchor::Completion;
             }
+            return Some(rule);
         } else {
             return current.cloned();
         }
     }
 }
 
-pub fn read_optional_due_date() -> Option<DateTime<Utc>> {
+pub fn read_optional_due_date() -> Option<Due> {
+    read_optional_date("due date")
+}
+
+/// Asks for a date such as "scheduled date" until it parses or is skipped.
+pub fn read_optional_date(what: &str) -> Option<Due> {
+    let date_format = &Config::current().date_format;
     println!(
         "{}",
-        "Enter due date (YYY-MM-DD, press Enter to skip):"
+        format!("Enter {what} ({date_format}, \"tomorrow\", \"next fri\", \"in 3 days\"…, press Enter to skip):")
             .blue()
             .bold()
     );
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    let trimmed = input.trim();
+    loop {
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).unwrap();
+        let trimmed = input.trim();
 
-    if trimmed.is_empty() {
-        None
-    } else {
-        match NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
-            Ok(nd) => {
-                // Default time to 00:00
-                let ndt = nd
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap_or_else(|| panic!("{}", "❌  Invalid time components".red()));
-                Some(DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc))
-            }
-            Err(_) => {
-                println!("{}", "⚠️ Invalid date format, skipping.".yellow());
-                None
-            }
+        if trimmed.is_empty() {
+            return None;
+        }
+        match parse_due(trimmed, date_format) {
+            Ok(due) => return Some(due),
+            Err(error) => println!("{}", format!("⚠️ {error}, try again.").yellow()),
+        }
+    }
+}
+
+/// Asks for a filter expression until it parses or is skipped.
+pub fn read_optional_filter() -> Option<Filter> {
+    println!(
+        "{}",
+        "Enter a filter, e.g. priority:high and (tag:work or tag:urgent) and due<+3d \
+         (press Enter to skip):"
+            .blue()
+            .bold()
+    );
+    loop {
+        let input = read_optional_input::<String>()?;
+        match Filter::parse(&input) {
+            Ok(filter) => return Some(filter),
+            Err(error) => println!("{}", format!("⚠️ {error}\nTry again:").yellow()),
         }
     }
 }
@@ -596,22 +784,30 @@ pub fn read_optional_tags() -> Option<Vec<String>> {
     }
 }
 
-pub fn read_optional_uuid(prompt: &str) -> Option<Uuid> {
+/// Reads a todo ID, number or ID prefix, resolved against `ids`.
+pub fn read_optional_id(ids: &IdIndex, prompt: &str) -> Option<Uuid> {
     println!("{}", prompt.blue().bold());
-    read_optional_input::<Uuid>()
+    let input = read_optional_input::<String>()?;
+    ids.resolve(&input)
+        .inspect_err(|error| println!("{}", format!("⚠️ {error}, skipping.").yellow()))
+        .ok()
 }
 
-pub fn read_optional_uuids(prompt: &str) -> Option<Vec<Uuid>> {
+pub fn read_optional_ids(ids: &IdIndex, prompt: &str) -> Option<Vec<Uuid>> {
     println!("{}", prompt.blue().bold());
     if let Some(input) = read_optional_input::<String>() {
-        let ids: Vec<Uuid> = input
+        let resolved: Vec<Uuid> = input
             .split(',')
-            .filter_map(|s| Uuid::parse_str(s.trim()).ok())
+            .filter_map(|s| {
+                ids.resolve(s)
+                    .inspect_err(|error| println!("{}", format!("⚠️ {error}, skipping.").yellow()))
+                    .ok()
+            })
             .collect();
-        if ids.is_empty() {
+        if resolved.is_empty() {
             None
         } else {
-            Some(ids)
+            Some(resolved)
         }
     } else {
         None
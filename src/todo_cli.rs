@@ -1,136 +1,155 @@
+use crate::dates::Zone;
+use crate::error::{TodoError, TodoResult};
+use crate::filter::Filter;
+use crate::fulltext::SearchIndex;
+use crate::ids::IdIndex;
 use crate::input::{AddTodoInput, SearchTodoInput, UpdateTodoInput};
+use crate::output::{OutputFormat, OutputOptions, print_todos};
+use crate::series;
 use crate::sortby::SortBy;
-use crate::storage::{load_todos_from_file, save_todos_to_file};
+use crate::status::Status;
+use crate::storage::{TodoQuery, TodoStore};
 use crate::todo::Todo;
 use chrono::Utc;
 use colored::*;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
-pub fn add_todo_cli(file_path: &str, todo_input: AddTodoInput) {
-    let mut todos = load_todos_from_file(file_path);
-
+pub fn add_todo_cli(store: &mut dyn TodoStore, todo_input: AddTodoInput) -> TodoResult<Uuid> {
     let todo = Todo::new(todo_input);
+    let id = todo.id;
 
-    if todos.contains_key(&todo.id) {
-        println!(
-            "{}",
-            "❌ A todo with this ID already exists. Try again."
-                .red()
-                .bold()
-        );
-        return;
+    if !store.insert(todo)? {
+        return Err(TodoError::AlreadyExists(id));
     }
-
-    todos.insert(todo.id, todo);
-    save_todos_to_file(&todos, file_path);
     println!("{}", "✅ Todo added successfully".green().bold());
+    Ok(id)
 }
 
-pub fn list_todos_cli(file_path: &str, sort_by: &SortBy) {
-    let todos = load_todos_from_file(file_path);
-
-    if todos.is_empty() {
-        println!("{}", "❌ No todos found.".red().bold());
-        return;
+/// Lists the actionable todos matching `filter`, or with `all` also the
+/// ones still waiting (see [`Todo::is_deferred`]).
+pub fn list_todos_cli(
+    store: &dyn TodoStore,
+    sort_by: &SortBy,
+    all: bool,
+    filter: Option<&Filter>,
+    output: &OutputOptions,
+) -> TodoResult<()> {
+    let (todos, ids) = find_with_ids(store, &filter.map(Filter::query).unwrap_or_default())?;
+    let mut todo_list: Vec<&Todo> = todos
+        .values()
+        .filter(|t| filter.is_none_or(|filter| filter.matches(t)))
+        .collect();
+    if !all {
+        let matching = todo_list.len();
+        todo_list.retain(|t| !t.is_deferred());
+        let hidden = matching - todo_list.len();
+        if hidden > 0 {
+            log::info!("{hidden} deferred todo(s) hidden, use --all to show them");
+        }
     }
 
-    let mut todo_list: Vec<&Todo> = todos.values().collect();
-
     match sort_by {
         SortBy::Priority => todo_list.sort_by_key(|t| t.priority),
-        SortBy::Status => todo_list.sort_by_key(|t| t.status),
+        SortBy::Status => todo_list.sort_by(|a, b| a.status.cmp(&b.status)),
         SortBy::Created => todo_list.sort_by_key(|t| t.created_at),
+        SortBy::Updated => todo_list.sort_by_key(|t| t.updated_at),
+        SortBy::Started => todo_list.sort_by_key(|t| (t.started_at.is_none(), t.started_at)),
+        SortBy::Completed => todo_list.sort_by_key(|t| (t.completed_at.is_none(), t.completed_at)),
         SortBy::DueDate => todo_list.sort_by_key(|t| t.due_date),
-        SortBy::Overdue => {
-            let now = Utc::now();
-            todo_list.sort_by_key(|t| match t.due_date {
-                Some(due) if due < now => 0,
-                Some(_) => 1,
-                None => 2,
-            });
-        }
+        SortBy::Overdue => todo_list.sort_by_key(|t| match t.due_date {
+            Some(_) if t.is_overdue() => 0,
+            Some(_) => 1,
+            None => 2,
+        }),
     }
 
-    println!(
-        "{}",
-        format!("--- Todos (sorted by {sort_by}) ---")
-            .bold()
-            .blue()
-            .underline()
-    );
-    for todo in &todo_list {
-        let priority_str = todo.priority.to_string();
-        let priority_color = match priority_str.as_str() {
-            "High" => priority_str.red().bold(),
-            "Medium" => priority_str.yellow(),
-            "Low" => priority_str.green(),
-            _ => priority_str.normal(),
-        };
-
-        let status_str = todo.status.to_string();
-        let status_color = match status_str.as_str() {
-            "Pending" => status_str.red().bold(),
-            "In Progress" => status_str.yellow(),
-            "Done" => status_str.green(),
-            _ => status_str.normal(),
-        };
-
-        println!("{:<10} {}", "ID:".bold(), todo.id.to_string().cyan());
-        println!("{:<10} {}", "Title:".bold(), todo.title.bold());
-        println!("{:<10} {}", "Priority:".bold(), priority_color);
-        println!("{:<10} {}", "Status:".bold(), status_color);
+    if todo_list.is_empty() {
+        log::warn!("❌ No todos found.");
+        if output.format == OutputFormat::Human {
+            return Ok(());
+        }
+    } else if output.format == OutputFormat::Human {
         println!(
-            "{:<10} {}",
-            "Description:".bold(),
-            todo.description.as_deref().unwrap_or("None")
+            "{}",
+            format!("--- Todos (sorted by {sort_by}) ---")
+                .bold()
+                .blue()
+                .underline()
         );
-        println!("{:<10} {}", "Created:".bold(), todo.created_at);
-
-        if let Some(due) = todo.due_date {
-            let mut overdue_str = due.to_string();
-            overdue_str.push_str("⚠️ Overdue!");
-            if due < Utc::now() {
-                println!("{:<10} {}", "Due Date:".bold(), overdue_str.red());
-            } else {
-                println!("{:<10} {}", "Due Date:".bold(), due.to_string().yellow());
-            }
-        }
+    }
+    print_todos(&todo_list, &ids, output)?;
+    Ok(())
+}
 
-        if let Some(tags) = &todo.tags {
-            println!("{:<10} {}", "Tags:".bold(), tags.join(", ").cyan());
-        }
+pub fn search_todo_cli(
+    store: &dyn TodoStore,
+    todo_input: SearchTodoInput,
+    output: &OutputOptions,
+) -> TodoResult<()> {
+    let (todos, ids) = find_with_ids(store, &store_query(&todo_input))?;
+    let results = search_todos(&todos, &ids, todo_input)?;
 
-        if let Some(pid) = todo.parent_id {
-            println!("{:<10} {}", "Parent ID:".bold(), pid.to_string().blue());
+    if results.is_empty() {
+        log::warn!("⚠️ No todos found with the given filters.");
+        if output.format == OutputFormat::Human {
+            return Ok(());
         }
+    } else if output.format == OutputFormat::Human {
+        println!(
+            "{}",
+            format!("Found {} todo(s):", results.len()).bold().blue()
+        );
+    }
+    print_todos(&results, &ids, output)?;
+    Ok(())
+}
 
-        if let Some(subs) = &todo.subtasks {
-            let subs_str: Vec<String> = subs.iter().map(|id| id.to_string()).collect();
-            println!(
-                "{:<10} {}",
-                "Subtasks:".bold(),
-                subs_str.join(", ").purple()
-            );
-        }
+/// The todos matching `query` with the index of all todos, which without
+/// any condition can be built from the todos themselves.
+fn find_with_ids(
+    store: &dyn TodoStore,
+    query: &TodoQuery,
+) -> TodoResult<(HashMap<Uuid, Todo>, IdIndex)> {
+    let todos = store.find(query)?;
+    let ids = if *query == TodoQuery::default() {
+        IdIndex::new(&todos)
+    } else {
+        store.id_index()?
+    };
+    Ok((todos, ids))
+}
 
-        if let Some(rec) = &todo.recurrence {
-            println!("{:<10} {}", "Recurrence:".bold(), rec.to_string().yellow());
-        }
-        println!();
+/// The part of a search the store can answer from its indexes.
+fn store_query(todo_input: &SearchTodoInput) -> TodoQuery {
+    // A series is found through its first todo, which may not match.
+    if todo_input.series.is_some() {
+        return TodoQuery::default();
+    }
+    let filter = todo_input
+        .filter
+        .as_ref()
+        .map(Filter::query)
+        .unwrap_or_default();
+    TodoQuery {
+        status: todo_input.status.clone().or(filter.status),
+        priority: todo_input.priority.or(filter.priority),
+        due_before: todo_input.due_before.or(filter.due_before),
     }
 }
 
-pub fn search_todo_cli(file_path: &str, todo_input: SearchTodoInput) {
-    let todos = load_todos_from_file(file_path);
+/// The todos matching every filter set in `todo_input`, best match first
+/// if it has a full-text query. `ids` resolves the IDs in `todo_input`.
+pub fn search_todos<'a>(
+    todos: &'a HashMap<Uuid, Todo>,
+    ids: &IdIndex,
+    todo_input: SearchTodoInput,
+) -> TodoResult<Vec<&'a Todo>> {
     let mut results: Vec<&Todo> = todos.values().collect();
 
     if let Some(id_str) = todo_input.id {
-        if let Ok(uuid) = Uuid::parse_str(&id_str) {
-            results.retain(|t| t.id == uuid);
-        } else {
-            println!("{}", format!("⚠️ Invalid UUID format: {id_str}").red());
-            return;
-        }
+        let uuid = ids.resolve(&id_str)?;
+        results.retain(|t| t.id == uuid);
     }
 
     if let Some(title_query) = todo_input.title {
@@ -148,8 +167,22 @@ pub fn search_todo_cli(file_path: &str, todo_input: SearchTodoInput) {
         results.retain(|t| t.status == s);
     }
 
+    let zone = Zone::current();
     if let Some(due) = todo_input.due_date {
-        results.retain(|t| t.due_date == Some(due));
+        results.retain(|t| t.due_date.is_some_and(|d| d.date(zone) == due.date(zone)));
+    }
+
+    if let Some(bound) = todo_input.due_before {
+        results.retain(|t| t.due_date.is_some_and(|d| d.is_before(bound, zone)));
+    }
+
+    if let Some(bound) = todo_input.due_after {
+        results.retain(|t| t.due_date.is_some_and(|d| d.is_after(bound, zone)));
+    }
+
+    if let Some(range) = todo_input.due {
+        let now = Utc::now();
+        results.retain(|t| range.matches(t.due_date, now, zone));
     }
 
     if let Some(rec) = todo_input.recurrence {
@@ -170,118 +203,486 @@ pub fn search_todo_cli(file_path: &str, todo_input: SearchTodoInput) {
         results.retain(|t| t.parent_id == Some(pid));
     }
 
-    if results.is_empty() {
-        println!("{}", "⚠️ No todos found with the given filters.".yellow());
-    } else {
-        println!(
-            "{}",
-            format!("Found {} todo(s):", results.len()).bold().blue()
-        );
-        for todo in results {
-            let priority_str = todo.priority.to_string();
-            let priority_color = match priority_str.as_str() {
-                "High" => priority_str.red().bold(),
-                "Medium" => priority_str.yellow(),
-                "Low" => priority_str.green(),
-                _ => priority_str.normal(),
-            };
+    if let Some(since) = todo_input.created_since {
+        results.retain(|t| t.created_at >= since);
+    }
 
-            let status_str = todo.status.to_string();
-            let status_color = match status_str.as_str() {
-                "Pending" => status_str.red().bold(),
-                "In Progress" => status_str.yellow(),
-                "Done" => status_str.green(),
-                _ => status_str.normal(),
-            };
-            println!("{:<10} {}", "ID:".bold(), todo.id.to_string().cyan());
-            println!("{:<10} {}", "Title:".bold(), todo.title.bold());
-            println!("{:<10} {}", "Priority:".bold(), priority_color);
-            println!("{:<10} {}", "Status:".bold(), status_color);
-            println!(
-                "{:<10} {}",
-                "Description:".bold(),
-                todo.description.as_deref().unwrap_or("None")
-            );
-            println!("{:<10} {}", "Created:".bold(), todo.created_at);
-            if let Some(due) = todo.due_date {
-                let mut overdue_str = due.to_string();
-                overdue_str.push_str(" ⚠️ Overdue!");
-                if due < Utc::now() {
-                    println!("{:<10} {}", "Due Date:".bold(), overdue_str.red());
-                } else {
-                    println!("{:<10} {}", "Due Date:".bold(), due.to_string().yellow());
-                }
-            }
+    if let Some(since) = todo_input.updated_since {
+        results.retain(|t| t.updated_at >= since);
+    }
 
-            if let Some(tags) = &todo.tags {
-                println!("{:<10} {}", "Tags:".bold(), tags.join(", ").cyan());
-            }
+    if let Some(since) = todo_input.started_since {
+        results.retain(|t| t.started_at.is_some_and(|at| at >= since));
+    }
 
-            if let Some(pid) = todo.parent_id {
-                println!("{:<10} {}", "Parent ID:".bold(), pid.to_string().blue());
-            }
+    if let Some(since) = todo_input.completed_since {
+        results.retain(|t| t.completed_at.is_some_and(|at| at >= since));
+    }
 
-            if let Some(subs) = &todo.subtasks {
-                let subs_str: Vec<String> = subs.iter().map(|id| id.to_string()).collect();
-                println!(
-                    "{:<10} {}",
-                    "Subtasks:".bold(),
-                    subs_str.join(", ").purple()
-                );
-            }
+    if let Some(member) = todo_input.series {
+        let series: HashSet<Uuid> = series::history(todos, ids.resolve(&member)?)
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        results.retain(|t| series.contains(&t.id));
+    }
 
-            if let Some(rec) = &todo.recurrence {
-                println!("{:<10} {}", "Recurrence:".bold(), rec.to_string().yellow());
-            }
-            println!();
-        }
+    if let Some(filter) = todo_input.filter {
+        results.retain(|t| filter.matches(t));
     }
-}
 
-pub fn update_todo_cli(file_path: &str, todo_input: UpdateTodoInput) -> bool {
-    let mut todos = load_todos_from_file(file_path);
+    if let Some(query) = todo_input.text {
+        results = SearchIndex::new(results)
+            .search(&query)
+            .into_iter()
+            .map(|(id, _)| &todos[&id])
+            .collect();
+    }
+    Ok(results)
+}
 
-    if let Some(todo) = todos.get_mut(&todo_input.id) {
-        if let Some(title) = todo_input.new_title {
-            todo.title = title
+/// Applies the update and returns the next occurrence if it completed a
+/// recurring todo.
+pub fn update_todo_cli(
+    store: &mut dyn TodoStore,
+    todo_input: UpdateTodoInput,
+) -> TodoResult<Option<Todo>> {
+    let mut outcome = Ok(None);
+    store.transaction(&mut |todos| {
+        let Some(mut todo) = todos.get(&todo_input.id).cloned() else {
+            outcome = Err(TodoError::NotFound(todo_input.id));
+            return false;
+        };
+        if let Some(status) = &todo_input.new_status
+            && let Err(error) = todo.status.check_transition(status)
+        {
+            outcome = Err(error);
+            return false;
         }
-        if let Some(desc) = todo_input.new_description {
-            todo.description = Some(desc)
+        apply_update(&mut todo, &todo_input);
+        outcome = save_edit(todos, todo);
+        outcome.is_ok()
+    })?;
+    outcome
+}
+
+/// Puts `edited` in place of the stored todo with its ID: the update path
+/// of both the CLI and the menu. Fails without changing anything if a new
+/// parent or subtask list would make a todo its own ancestor. Returns the
+/// next occurrence if the edit completed a recurring todo.
+pub fn save_edit(todos: &mut HashMap<Uuid, Todo>, edited: Todo) -> TodoResult<Option<Todo>> {
+    let id = edited.id;
+    let Some(current) = todos.get(&id) else {
+        return Err(TodoError::NotFound(id));
+    };
+    check_hierarchy(todos, current, &edited)?;
+    let status_changed = current.status != edited.status;
+    todos.insert(id, edited);
+    if !status_changed {
+        return Ok(None);
+    }
+    Ok(series::spawn_next(todos, id).and_then(|next| todos.get(&next).cloned()))
+}
+
+/// Rejects a changed parent or subtask list that would make a todo its own
+/// ancestor.
+fn check_hierarchy(todos: &HashMap<Uuid, Todo>, current: &Todo, edited: &Todo) -> TodoResult<()> {
+    let id = edited.id;
+    if let Some(parent) = edited.parent_id
+        && current.parent_id != edited.parent_id
+        && (parent == id || ancestors(todos, parent).contains(&id))
+    {
+        return Err(TodoError::Cycle { id, parent });
+    }
+    if let Some(subtasks) = &edited.subtasks
+        && current.subtasks != edited.subtasks
+    {
+        let mut above = edited
+            .parent_id
+            .map(|p| ancestors(todos, p))
+            .unwrap_or_default();
+        above.extend(edited.parent_id);
+        above.insert(id);
+        if let Some(&sub) = subtasks.iter().find(|sub| above.contains(sub)) {
+            return Err(TodoError::Cycle {
+                id: sub,
+                parent: id,
+            });
         }
-        if let Some(p) = todo_input.new_priority {
-            todo.priority = p;
+    }
+    Ok(())
+}
+
+/// Every todo reachable by following `parent_id` upwards from `id`.
+fn ancestors(todos: &HashMap<Uuid, Todo>, id: Uuid) -> HashSet<Uuid> {
+    let mut seen = HashSet::new();
+    let mut current = todos.get(&id).and_then(|t| t.parent_id);
+    while let Some(parent) = current {
+        if !seen.insert(parent) {
+            break;
         }
-        if let Some(s) = todo_input.new_status {
-            todo.status = s;
+        current = todos.get(&parent).and_then(|t| t.parent_id);
+    }
+    seen
+}
+
+fn apply_update(todo: &mut Todo, todo_input: &UpdateTodoInput) {
+    let original = todo.clone();
+    if let Some(title) = &todo_input.new_title {
+        todo.title = title.clone();
+    }
+    if let Some(desc) = &todo_input.new_description {
+        todo.description = Some(desc.clone());
+    }
+    if let Some(p) = todo_input.new_priority {
+        todo.priority = p;
+    }
+    if let Some(status) = &todo_input.new_status {
+        todo.set_status(status.clone());
+    }
+    if let Some(d) = todo_input.new_due_date {
+        todo.due_date = Some(d);
+    }
+    if let Some(d) = todo_input.new_scheduled {
+        todo.scheduled = Some(d);
+    }
+    if let Some(d) = todo_input.new_wait_until {
+        todo.wait_until = Some(d);
+    }
+    if let Some(tags) = &todo_input.new_tags {
+        todo.tags = Some(tags.clone());
+    }
+    if let Some(rec) = &todo_input.new_recurrence {
+        todo.recurrence = Some(rec.clone());
+    }
+    if let Some(pid) = todo_input.new_parent_id {
+        todo.parent_id = Some(pid);
+    }
+    if let Some(subs) = &todo_input.new_subtasks {
+        todo.subtasks = Some(subs.clone());
+    }
+    if *todo != original {
+        todo.touch();
+    }
+}
+
+/// A status change made by [`set_status_cli`]. `from == to` means the
+/// todo already had the requested status and was left alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusChange {
+    pub id: Uuid,
+    pub title: String,
+    pub from: Status,
+    pub to: Status,
+    /// The occurrence created because this change completed a recurring
+    /// todo.
+    pub next: Option<Todo>,
+}
+
+/// Moves every todo in `ids` to `status` in one transaction. Nothing is
+/// changed if any of the IDs does not exist or may not make that change.
+pub fn set_status_cli(
+    store: &mut dyn TodoStore,
+    ids: &[Uuid],
+    status: Status,
+) -> TodoResult<Vec<StatusChange>> {
+    // The same todo given twice is changed and reported once.
+    let mut seen = HashSet::new();
+    let ids: Vec<Uuid> = ids.iter().copied().filter(|id| seen.insert(*id)).collect();
+    let mut outcome = Ok(Vec::new());
+    store.transaction(&mut |todos| {
+        let mut changes = Vec::new();
+        for &id in &ids {
+            let Some(todo) = todos.get(&id) else {
+                outcome = Err(TodoError::NotFound(id));
+                return false;
+            };
+            if let Err(error) = todo.status.check_transition(&status) {
+                outcome = Err(error);
+                return false;
+            }
+            changes.push(StatusChange {
+                id,
+                title: todo.title.clone(),
+                from: todo.status.clone(),
+                to: status.clone(),
+                next: None,
+            });
         }
-        if let Some(d) = todo_input.new_due_date {
-            todo.due_date = Some(d);
+        for change in &mut changes {
+            if let Some(todo) = todos.get_mut(&change.id)
+                && todo.set_status(status.clone())
+            {
+                change.next =
+                    series::spawn_next(todos, change.id).and_then(|next| todos.get(&next).cloned());
+            }
         }
-        if let Some(tags) = todo_input.new_tags {
-            todo.tags = Some(tags);
+        let changed = changes.iter().any(|change| change.from != change.to);
+        outcome = Ok(changes);
+        changed
+    })?;
+    outcome
+}
+
+pub fn delete_todo_cli(store: &mut dyn TodoStore, id: Uuid) -> TodoResult<()> {
+    if store.delete(id)? {
+        Ok(())
+    } else {
+        Err(TodoError::NotFound(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        due::{Due, DueFilter},
+        priority::Priority,
+        storage::MemoryStore,
+    };
+
+    fn add(store: &mut MemoryStore, title: &str, parent_id: Option<Uuid>) -> Uuid {
+        add_todo_cli(
+            store,
+            AddTodoInput {
+                parent_id,
+                ..AddTodoInput::new(title)
+            },
+        )
+        .unwrap()
+    }
+
+    fn update(id: Uuid) -> UpdateTodoInput {
+        UpdateTodoInput {
+            id,
+            new_title: None,
+            new_description: None,
+            new_priority: None,
+            new_status: None,
+            new_due_date: None,
+            new_scheduled: None,
+            new_wait_until: None,
+            new_recurrence: None,
+            new_tags: None,
+            new_parent_id: None,
+            new_subtasks: None,
         }
-        if let Some(rec) = todo_input.new_recurrence {
-            todo.recurrence = Some(rec);
+    }
+
+    #[test]
+    fn missing_todos_are_not_found() {
+        let mut store = MemoryStore::default();
+        let id = Uuid::new_v4();
+        assert!(matches!(
+            update_todo_cli(&mut store, update(id)),
+            Err(TodoError::NotFound(missing)) if missing == id
+        ));
+        assert!(matches!(
+            delete_todo_cli(&mut store, id),
+            Err(TodoError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_parent_and_subtask_cycles() {
+        let mut store = MemoryStore::default();
+        let root = add(&mut store, "Root", None);
+        let child = add(&mut store, "Child", Some(root));
+
+        let mut own_parent = update(root);
+        own_parent.new_parent_id = Some(root);
+        let mut under_child = update(root);
+        under_child.new_parent_id = Some(child);
+        let mut parent_as_subtask = update(child);
+        parent_as_subtask.new_subtasks = Some(vec![root]);
+
+        for input in [own_parent, under_child, parent_as_subtask] {
+            assert!(matches!(
+                update_todo_cli(&mut store, input),
+                Err(TodoError::Cycle { .. })
+            ));
         }
-        if let Some(pid) = todo_input.new_parent_id {
-            todo.parent_id = Some(pid);
+        assert_eq!(store.get(root).unwrap().unwrap().parent_id, None);
+    }
+
+    #[test]
+    fn edits_saved_by_the_menu_are_checked_too() {
+        let mut store = MemoryStore::default();
+        let root = add(&mut store, "Root", None);
+        let child = add(&mut store, "Child", Some(root));
+        let mut todos = store.load().unwrap();
+
+        let mut edited = todos[&root].clone();
+        edited.parent_id = Some(child);
+        assert!(matches!(
+            save_edit(&mut todos, edited),
+            Err(TodoError::Cycle { .. })
+        ));
+        assert_eq!(todos[&root].parent_id, None);
+
+        let mut edited = todos[&root].clone();
+        edited.title = "Renamed".into();
+        assert_eq!(save_edit(&mut todos, edited).unwrap(), None);
+        assert_eq!(todos[&root].title, "Renamed");
+    }
+
+    #[test]
+    fn status_commands_record_timestamps() {
+        let mut store = MemoryStore::default();
+        let a = add(&mut store, "A", None);
+        let b = add(&mut store, "B", None);
+
+        let changes = set_status_cli(&mut store, &[a, b, a], Status::InProgress).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|c| c.from == Status::Pending));
+        let started = store.get(a).unwrap().unwrap().started_at.unwrap();
+
+        set_status_cli(&mut store, &[a], Status::Done).unwrap();
+        let done = store.get(a).unwrap().unwrap();
+        assert_eq!(done.started_at, Some(started));
+        assert!(done.completed_at.is_some());
+
+        let again = set_status_cli(&mut store, &[a], Status::Done).unwrap();
+        assert_eq!(again[0].from, again[0].to);
+
+        set_status_cli(&mut store, &[a], Status::Pending).unwrap();
+        assert_eq!(store.get(a).unwrap().unwrap().completed_at, None);
+
+        assert!(matches!(
+            set_status_cli(&mut store, &[b, Uuid::new_v4()], Status::Cancelled),
+            Err(TodoError::NotFound(_))
+        ));
+        assert_eq!(store.get(b).unwrap().unwrap().status, Status::InProgress);
+    }
+
+    #[test]
+    fn finishing_a_recurring_todo_schedules_the_next_one() {
+        let mut store = MemoryStore::default();
+        let id = add(&mut store, "Standup", None);
+        let daily = |new_status| UpdateTodoInput {
+            new_status,
+            new_recurrence: Some("daily".parse().unwrap()),
+            ..update(id)
+        };
+        assert_eq!(update_todo_cli(&mut store, daily(None)).unwrap(), None);
+
+        let next = update_todo_cli(&mut store, daily(Some(Status::Done)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.status, Status::Pending);
+        assert_eq!(next.series_id, Some(id));
+
+        let changes = set_status_cli(&mut store, &[next.id], Status::Done).unwrap();
+        let third = changes[0].next.as_ref().unwrap();
+        assert!(third.due_date > next.due_date);
+        assert_eq!(store.load().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn waiting_todos_are_deferred_until_their_date() {
+        let mut store = MemoryStore::default();
+        let id = add(&mut store, "Renew passport", None);
+        assert!(!store.get(id).unwrap().unwrap().is_deferred());
+
+        let today = Zone::current().today();
+        for (days, deferred) in [(1, true), (0, false)] {
+            let mut input = update(id);
+            input.new_wait_until = Some(Due::Date(today + chrono::Days::new(days)));
+            update_todo_cli(&mut store, input).unwrap();
+            assert_eq!(store.get(id).unwrap().unwrap().is_deferred(), deferred);
         }
-        if let Some(subs) = todo_input.new_subtasks {
-            todo.subtasks = Some(subs);
+    }
+
+    /// Past, Today and Later are high priority and due three days ago,
+    /// today and in 30 days. Someday has no due date.
+    fn dated_todos() -> HashMap<Uuid, Todo> {
+        let mut store = MemoryStore::default();
+        let today = Zone::current().today();
+        for (title, days) in [("Past", -3), ("Today", 0), ("Later", 30)] {
+            let id = add(&mut store, title, None);
+            let mut input = update(id);
+            input.new_due_date = Some(Due::Date(today + chrono::TimeDelta::days(days)));
+            input.new_priority = Some(Priority::High);
+            update_todo_cli(&mut store, input).unwrap();
         }
-        save_todos_to_file(&todos, file_path);
-        true
-    } else {
-        false
+        add(&mut store, "Someday", None);
+        store.load().unwrap()
     }
-}
 
-pub fn delete_todo_cli(file_path: &str, id: Uuid) -> bool {
-    let mut todos = load_todos_from_file(file_path);
-    if todos.remove(&id).is_some() {
-        save_todos_to_file(&todos, file_path);
-        true
-    } else {
-        false
+    fn search_titles(todos: &HashMap<Uuid, Todo>, input: SearchTodoInput) -> Vec<String> {
+        let mut titles: Vec<String> = search_todos(todos, &IdIndex::new(todos), input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.title.clone())
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn search_filters_by_due_ranges_and_creation() {
+        let todos = dated_todos();
+        let today = Zone::current().today();
+        let titles = |input| search_titles(&todos, input);
+        let query = SearchTodoInput::default();
+
+        let before = SearchTodoInput {
+            due_before: Some(Due::Date(today + chrono::Days::new(1))),
+            ..query.clone()
+        };
+        assert_eq!(titles(before), ["Past", "Today"]);
+        let after = SearchTodoInput {
+            due_after: Some(Due::Date(today)),
+            priority: Some(Priority::High),
+            ..query.clone()
+        };
+        assert_eq!(titles(after), ["Later"]);
+        let overdue = SearchTodoInput {
+            due: Some(DueFilter::Overdue),
+            ..query.clone()
+        };
+        assert_eq!(titles(overdue), ["Past"]);
+        let undated = SearchTodoInput {
+            due: Some(DueFilter::None),
+            ..query.clone()
+        };
+        assert_eq!(titles(undated), ["Someday"]);
+        let created = SearchTodoInput {
+            created_since: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..query
+        };
+        assert!(titles(created).is_empty());
+    }
+
+    #[test]
+    fn search_filters_by_expression() {
+        let todos = dated_todos();
+        let expression = SearchTodoInput {
+            filter: Some(Filter::parse("due<+1d or not priority:high").unwrap()),
+            ..SearchTodoInput::default()
+        };
+        assert_eq!(
+            search_titles(&todos, expression),
+            ["Past", "Someday", "Today"]
+        );
+    }
+
+    #[test]
+    fn search_combines_text_with_other_filters() {
+        let todos = dated_todos();
+        let titles = |input| search_titles(&todos, input);
+        let query = SearchTodoInput::default();
+
+        let text = SearchTodoInput {
+            text: Some("somed* OR later".parse().unwrap()),
+            ..query.clone()
+        };
+        assert!(titles(text).is_empty(), "every word has to match");
+        let text = SearchTodoInput {
+            text: Some("LATER".parse().unwrap()),
+            priority: Some(Priority::High),
+            ..query
+        };
+        assert_eq!(titles(text), ["Later"]);
     }
 }
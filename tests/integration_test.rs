@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use todoscope::error::TodoError;
 use todoscope::priority::Priority;
 use todoscope::status::Status;
 use todoscope::{add_todo_to_map, delete_todo_by_id, update_todo_in_map};
@@ -13,7 +14,8 @@ fn add_todo_inserts_item() {
         "Finish the Rust book".into(),
         Priority::High,
         Status::Pending,
-    );
+    )
+    .unwrap();
     assert!(todos.contains_key(&id));
     assert_eq!(todos[&id].title, "Learn Rust");
 }
@@ -27,7 +29,8 @@ fn update_todo_changes_fields() {
         "Old Desc".into(),
         Priority::Low,
         Status::Pending,
-    );
+    )
+    .unwrap();
 
     let updated = update_todo_in_map(
         &mut todos,
@@ -38,7 +41,7 @@ fn update_todo_changes_fields() {
         Some(Status::Done),
     );
 
-    assert!(updated);
+    assert!(updated.is_ok());
     let todo = &todos[&id];
     assert_eq!(todo.title, "New Title");
     assert_eq!(todo.description.as_deref(), Some("New Desc"));
@@ -55,16 +58,17 @@ fn delete_todo_removes_item() {
         "To be deleted".into(),
         Priority::Medium,
         Status::Pending,
-    );
+    )
+    .unwrap();
 
     let deleted = delete_todo_by_id(&mut todos, id);
 
-    assert!(deleted);
+    assert_eq!(deleted.unwrap().title, "Temporary");
     assert!(!todos.contains_key(&id));
 }
 
 #[test]
-fn update_todo_nonexistent_returns_false() {
+fn update_todo_nonexistent_returns_not_found() {
     let mut todos = HashMap::new();
     let random_id = Uuid::new_v4();
 
@@ -77,5 +81,5 @@ fn update_todo_nonexistent_returns_false() {
         None,
     );
 
-    assert!(!updated);
+    assert!(matches!(updated, Err(TodoError::NotFound(id)) if id == random_id));
 }